@@ -0,0 +1,549 @@
+use std::cmp::{max, min};
+use std::collections::HashMap;
+use std::time::Instant;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::board::{MAX_BOARD_SIZE, Position};
+use crate::bot::{Bot, DEADLINE_CHECK_INTERVAL, DEFAULT_CACHE_SIZE};
+use crate::calibration::calibration;
+use crate::errors::Error;
+use crate::errors::Error::InvalidArgument;
+use crate::game::{Action, Game, normalize, Player, Variant};
+
+/// Number of empty squares from which moves are scored by solving the rest of the game
+pub const EXACT_EMPTIES: usize = 10;
+
+/// Default number of milliseconds the engine lets an analysis solve the end of the game exactly
+pub const DEFAULT_SOLVE_TIME: u64 = 5000;
+
+/// Number of actions in the principal variation of each move, including the move itself
+pub const PV_LENGTH: usize = 4;
+
+/// Highest disk margin a game on the largest board can end with
+const MAX_MARGIN: i32 = (MAX_BOARD_SIZE * MAX_BOARD_SIZE) as i32;
+
+/// Highest numbers of disks expected to be lost versus the best move for each classification on a board of 64
+/// squares, from good to mistake. They scale with the number of squares of other boards.
+const GOOD_LOSS: f32 = 2.0;
+const INACCURACY_LOSS: f32 = 4.0;
+const MISTAKE_LOSS: f32 = 8.0;
+
+/// Number of squares of the board the losses of the classifications are given for
+const STANDARD_SQUARES: f32 = 64.0;
+
+/// Score of a legal move for the player to move
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MoveAnalysis {
+    pub position: Position,
+    /// Evaluation after the move for the player making it, where 1 is the best and 0 is the worst
+    pub evaluation: f32,
+    /// Final disk margin of the player with perfect play, given when the end of the game is close enough to solve.
+    /// It is negated in anti games, so that a positive score wins in both variants.
+    pub exact_score: Option<i32>,
+    /// Final disk margin the player can expect, which is the exact score when there is one and is otherwise
+    /// estimated from the evaluation. It is negated in anti games like the exact score.
+    pub expected_margin: f32,
+    /// Whether no other move scores higher
+    pub best: bool,
+    /// Expected continuation starting with this move
+    pub pv: Vec<Position>,
+}
+
+/// How good a move was compared to the best move
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Classification {
+    Best,
+    Good,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl Classification {
+
+    /// Classifies a move expected to lose the given number of disks versus the best move on a board of the given
+    /// number of squares
+    pub fn new(loss: f32, squares: usize) -> Self {
+        let scale = squares as f32 / STANDARD_SQUARES;
+
+        if loss <= 0.0 {
+            Classification::Best
+        } else if loss <= GOOD_LOSS * scale {
+            Classification::Good
+        } else if loss <= INACCURACY_LOSS * scale {
+            Classification::Inaccuracy
+        } else if loss <= MISTAKE_LOSS * scale {
+            Classification::Mistake
+        } else {
+            Classification::Blunder
+        }
+    }
+}
+
+/// Review of a move played in a game
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MoveReview {
+    pub player: Player,
+    pub position: Position,
+    /// Evaluation of the position for the player before the move, which is the evaluation of the best move
+    pub evaluation_before: f32,
+    /// Evaluation of the position for the player after the move
+    pub evaluation_after: f32,
+    /// Best move the player had, which is the played move when it was one of the best
+    pub best_move: Position,
+    /// Disks the player can expect to lose versus the best move
+    pub loss: f32,
+    pub classification: Classification,
+}
+
+/// Summary of the moves of one player in a game
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct PlayerSummary {
+    pub player: Player,
+    /// Average accuracy of the moves, where 100 means that every move was one of the best
+    pub accuracy: f32,
+    pub best: usize,
+    pub good: usize,
+    pub inaccuracies: usize,
+    pub mistakes: usize,
+    pub blunders: usize,
+}
+
+/// Report of a game with every move reviewed
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GameReview {
+    pub moves: Vec<MoveReview>,
+    /// Summaries of the players who moved, dark first
+    pub players: Vec<PlayerSummary>,
+    /// Winner of the game, which is null for a draw or a game that isn't over
+    pub winner: Option<Player>,
+}
+
+/// Scores every legal move of the player to move with the given bot, best moves first.
+/// Moves are only solved exactly until the given deadline, if any, after which they are scored by the bot.
+pub fn analyze(bot: &mut Bot, game: &Game, deadline: Option<Instant>) -> Vec<MoveAnalysis> {
+    analyze_with_pv(bot, &mut Solver::new(deadline), game, PV_LENGTH)
+}
+
+/// Scores every legal move like analyze with the given solver, with principal variations of the given length
+fn analyze_with_pv(bot: &mut Bot, solver: &mut Solver, game: &Game, pv_length: usize) -> Vec<MoveAnalysis> {
+    let player = game.current_player();
+    let actions = game.actions(player).collect::<Vec<_>>();
+    let mut analyses = Vec::new();
+
+    // Either every move is solved or none is, so that exact scores are never ranked against evaluations
+    let solutions = actions.iter()
+        .map(|action| game.result(action))
+        .map(|result| if result.board().empty_positions().count() <= EXACT_EMPTIES {
+            solver.solve(&result, pv_length.saturating_sub(1))
+        } else {
+            None
+        })
+        .collect::<Option<Vec<_>>>();
+
+    for (i, action) in actions.into_iter().enumerate() {
+        let result = game.result(&action);
+
+        let (evaluation, exact_score, expected_margin, continuation) =
+            if let Some((margin, continuation)) = solutions.as_ref().map(|solutions| solutions[i].clone()) {
+                let margin = -margin;
+                let squares = result.board().size() * result.board().size();
+                let evaluation = 0.5 + margin as f32 / (2 * squares) as f32;
+                (evaluation, Some(margin), margin as f32, continuation)
+            } else {
+                let score = bot.search(&result);
+                let value = normalize(score, result.board().size());
+                let margin = calibration().predict_score(&result, score).expected_margin;
+                let (evaluation, margin) = if player == Player::Bot { (value, margin) } else { (1.0 - value, -margin) };
+                (evaluation, None, margin, bot.principal_variation(&result, pv_length.saturating_sub(1)))
+            };
+
+        let pv = [action.clone()].iter().chain(continuation.iter())
+            .take(max(pv_length, 1))
+            .map(|action| action.placement().clone())
+            .collect();
+
+        analyses.push(MoveAnalysis {
+            position: action.placement().clone(),
+            evaluation,
+            exact_score,
+            expected_margin,
+            best: false,
+            pv,
+        });
+    }
+
+    // Exact scores are compared first as they are more precise than the evaluations
+    analyses.sort_by(|a, b| {
+        b.exact_score.cmp(&a.exact_score)
+            .then(b.evaluation.total_cmp(&a.evaluation))
+            .then(a.position.cmp(&b.position))
+    });
+
+    if let Some(top) = analyses.first().map(|analysis| (analysis.exact_score, analysis.evaluation)) {
+        for analysis in analyses.iter_mut() {
+            analysis.best = (analysis.exact_score, analysis.evaluation) == top;
+        }
+    }
+
+    analyses
+}
+
+/// Reviews every move of a game played from the given start, passing the turn whenever the player to move
+/// has no actions. Moves are only solved exactly until the given deadline, if any.
+pub fn review(bot: &mut Bot, start: &Game, moves: &[Position], deadline: Option<Instant>)
+    -> Result<GameReview, Error> {
+    let mut solver = Solver::new(deadline);
+    let mut game = start.clone();
+    let mut reviews = Vec::new();
+    let squares = start.board().size() * start.board().size();
+
+    for (i, position) in moves.iter().enumerate() {
+        if !game.is_over() && game.actions(game.current_player()).next().is_none() {
+            game = game.pass();
+        }
+
+        let player = game.current_player();
+        let action = Action::parse(player, position.clone());
+        if game.is_over() || !game.actions(player).any(|act| act == action) {
+            return Err(InvalidArgument(format!("Move {} at {} is not legal for {}", i + 1, position, player)));
+        }
+
+        let analyses = analyze_with_pv(bot, &mut solver, &game, 1);
+        let best = &analyses[0];
+        let played = analyses.iter().find(|analysis| &analysis.position == position).unwrap();
+        let loss = if played.best { 0.0 } else { (best.expected_margin - played.expected_margin).max(0.0) };
+
+        reviews.push(MoveReview {
+            player,
+            position: position.clone(),
+            evaluation_before: best.evaluation,
+            evaluation_after: played.evaluation,
+            best_move: if played.best { position.clone() } else { best.position.clone() },
+            loss,
+            classification: Classification::new(loss, squares),
+        });
+        game = game.result(&action);
+    }
+
+    let players = [Player::Human, Player::Bot].into_iter()
+        .filter_map(|player| summarize(player, &reviews, squares))
+        .collect();
+
+    Ok(GameReview {
+        moves: reviews,
+        players,
+        winner: if game.is_over() { game.winner() } else { None },
+    })
+}
+
+/// Summarizes the reviewed moves of the given player on a board of the given number of squares, if they made any
+fn summarize(player: Player, reviews: &[MoveReview], squares: usize) -> Option<PlayerSummary> {
+    let mut summary = PlayerSummary { player, ..PlayerSummary::default() };
+    let mut total_accuracy = 0.0;
+    let mut num_moves = 0;
+    let mistake_loss = MISTAKE_LOSS * squares as f32 / STANDARD_SQUARES;
+
+    for review in reviews.iter().filter(|review| review.player == player) {
+        num_moves += 1;
+        // Blunders count as no accuracy at all
+        total_accuracy += 100.0 * (1.0 - review.loss / mistake_loss).max(0.0);
+
+        match review.classification {
+            Classification::Best => summary.best += 1,
+            Classification::Good => summary.good += 1,
+            Classification::Inaccuracy => summary.inaccuracies += 1,
+            Classification::Mistake => summary.mistakes += 1,
+            Classification::Blunder => summary.blunders += 1,
+        }
+    }
+
+    if num_moves == 0 {
+        return None;
+    }
+    summary.accuracy = total_accuracy / num_moves as f32;
+    Some(summary)
+}
+
+/// Exact solver of the end of games. It remembers the bounds it proves, so that the moves of a position and the
+/// positions of a review share their work, and gives up once its deadline passes.
+pub struct Solver {
+    bounds: HashMap<Game, (i32, i32)>,
+    deadline: Option<Instant>,
+    timed_out: bool,
+    num_nodes_expanded: u32,
+}
+
+impl Solver {
+
+    /// Creates a solver that gives up at the given deadline, if any
+    pub fn new(deadline: Option<Instant>) -> Self {
+        Self {
+            bounds: HashMap::new(),
+            deadline,
+            timed_out: false,
+            num_nodes_expanded: 0,
+        }
+    }
+
+    /// Returns the final disk margin of the player to move when both players play perfectly, with up to the given
+    /// number of actions leading to it, or None if the deadline passes first.
+    /// In anti games the margin counts the disks of the opponent minus those of the player, so higher is still better.
+    pub fn solve(&mut self, game: &Game, length: usize) -> Option<(i32, Vec<Action>)> {
+        let margin = self.search(game, -MAX_MARGIN - 1, MAX_MARGIN + 1);
+        if self.timed_out {
+            return None;
+        }
+
+        // Each action of the variation is one whose result keeps the margin, which the bounds make quick to find
+        let mut game = game.clone();
+        let mut target = margin;
+        let mut variation = Vec::new();
+        while variation.len() < length && !game.is_over() {
+            let actions = game.actions(game.current_player()).collect::<Vec<_>>();
+            if actions.is_empty() {
+                game = game.pass();
+                target = -target;
+                continue;
+            }
+
+            let best = actions.into_iter()
+                .map(|action| (game.result(&action), action))
+                .find(|(result, _)| -self.search(result, -target - 1, -target + 1) == target);
+            let Some((result, action)) = best else {
+                break;
+            };
+            game = result;
+            target = -target;
+            variation.push(action);
+        }
+
+        if self.timed_out { None } else { Some((margin, variation)) }
+    }
+
+    /// Checks if the deadline has passed, looking at the time only once in a while
+    fn is_out_of_time(&mut self) -> bool {
+        if !self.timed_out && self.num_nodes_expanded.is_multiple_of(DEADLINE_CHECK_INTERVAL) {
+            self.timed_out = self.deadline.is_some_and(|deadline| Instant::now() >= deadline);
+        }
+        self.timed_out
+    }
+
+    /// Returns the final disk margin of the player to move, which is exact when it lies between alpha and beta and
+    /// a bound otherwise. Its value is meaningless once the deadline has passed.
+    fn search(&mut self, game: &Game, mut alpha: i32, mut beta: i32) -> i32 {
+        let player = game.current_player();
+
+        if game.is_over() {
+            let margin = game.board().positions(player.disk()).count() as i32 -
+                game.board().positions(player.opponent().disk()).count() as i32;
+            return if game.variant() == Variant::Anti { -margin } else { margin };
+        } else if self.is_out_of_time() {
+            return alpha;
+        }
+
+        let (lower, upper) = self.bounds.get(game).copied().unwrap_or((-MAX_MARGIN, MAX_MARGIN));
+        if lower >= beta || lower == upper {
+            return lower;
+        } else if upper <= alpha {
+            return upper;
+        }
+        alpha = max(alpha, lower);
+        beta = min(beta, upper);
+        let window = (alpha, beta);
+
+        self.num_nodes_expanded += 1;
+
+        // Moves leaving the opponent fewest replies are searched first, as they tend to be best and cut the rest off
+        let mut results = game.actions(player)
+            .map(|action| {
+                let result = game.result(&action);
+                (result.actions(player.opponent()).count(), result)
+            })
+            .collect::<Vec<_>>();
+        results.sort_by_key(|(replies, _)| *replies);
+
+        let mut best = -MAX_MARGIN - 1;
+        if results.is_empty() {
+            best = -self.search(&game.pass(), -beta, -alpha);
+        }
+        for (_, result) in results {
+            best = max(best, -self.search(&result, -beta, -alpha));
+            alpha = max(alpha, best);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        if self.timed_out {
+            return best;
+        }
+        let bounds = if best <= window.0 {
+            (lower, best)
+        } else if best >= window.1 {
+            (best, upper)
+        } else {
+            (best, best)
+        };
+        if self.bounds.len() >= DEFAULT_CACHE_SIZE {
+            self.bounds.clear();
+        }
+        self.bounds.insert(game.clone(), bounds);
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use crate::analysis::{analyze, Classification, GOOD_LOSS, PV_LENGTH, review, Solver};
+    use crate::board::{Board, Position};
+    use crate::bot::Bot;
+    use crate::game::{Game, Phase};
+    use crate::game::Player::{Bot as BotPlayer, Human};
+
+    #[test]
+    fn analyze_midgame() {
+        let mut bot = Bot::new(2);
+        let analyses = analyze(&mut bot, &Game::parse(Board::new(), Human), None);
+
+        assert_eq!(analyses.len(), 4);
+        assert!(analyses[0].best);
+        for analysis in analyses.iter() {
+            assert_eq!(analysis.exact_score, None);
+            assert_eq!(analysis.pv.len(), PV_LENGTH);
+            assert_eq!(analysis.pv[0], analysis.position);
+            assert!((0.0..=1.0).contains(&analysis.evaluation));
+        }
+        // The opening moves are symmetric
+        assert!(analyses.iter().all(|analysis| analysis.best));
+    }
+
+    #[test]
+    fn analyze_endgame() {
+        // Only the corner flips the dark disks, after which the bot owns every disk
+        let board = Board::parse([
+            "EDLLLLLL",
+            "DDLLLLLL",
+            "LLLLLLLL",
+            "LLLLLLLL",
+            "LLLLLLLL",
+            "LLLLLLLL",
+            "LLLLLLLL",
+            "LLLLLLLE",
+        ].join("\n")).unwrap();
+        let game = Game::parse(board, BotPlayer);
+
+        let mut bot = Bot::new(1);
+        let analyses = analyze(&mut bot, &game, None);
+
+        assert_eq!(analyses.len(), 1);
+        assert_eq!(analyses[0].position, Position::new(0, 0));
+        assert!(analyses[0].best);
+
+        let (margin, variation) = Solver::new(None).solve(&game, usize::MAX).unwrap();
+        assert_eq!(analyses[0].exact_score, Some(63));
+        assert_eq!(analyses[0].evaluation, 0.5 + 63.0 / 128.0);
+        assert_eq!(margin, 63);
+        assert_eq!(variation[0].placement(), &Position::new(0, 0));
+        assert_eq!(analyses[0].pv.len(), variation.len());
+    }
+
+    #[test]
+    fn solve_endgame() {
+        let board = Board::parse([
+            "LLLDDD",
+            "LDDLDE",
+            "LLDDEE",
+            "DLDLLE",
+            "DDLDEE",
+            "EEDLEE",
+        ].join("\n")).unwrap();
+        let game = Game::parse(board, Human);
+
+        // The bounds remembered from the moves agree with the margin of the whole position
+        let mut solver = Solver::new(None);
+        let best = game.actions(Human)
+            .map(|action| -solver.solve(&game.result(&action), 0).unwrap().0)
+            .max()
+            .unwrap();
+        let (margin, variation) = solver.solve(&game, usize::MAX).unwrap();
+        assert_eq!(margin, best);
+        assert_eq!(Solver::new(None).solve(&game, 0).unwrap().0, margin);
+        assert!(!variation.is_empty());
+
+        // Past the deadline nothing is solved, so every move is scored by the bot
+        assert!(Solver::new(Some(Instant::now())).solve(&game, 0).is_none());
+        let analyses = analyze(&mut Bot::new(1), &game, Some(Instant::now()));
+        assert!(!analyses.is_empty());
+        assert!(analyses.iter().all(|analysis| analysis.exact_score.is_none()));
+        assert!(analyze(&mut Bot::new(1), &game, None).iter().all(|analysis| analysis.exact_score.is_some()));
+    }
+
+    #[test]
+    fn review_game() {
+        // Dark can flip the row of light disks from different squares, some of which give away a corner
+        let board = Board::parse([
+            "EDDDDDDE",
+            "ELLLLLLE",
+            "EEEEEEEE",
+            "EEEDLEEE",
+            "EEELDEEE",
+            "EEEEEEEE",
+            "EEEEEEEE",
+            "EEEEEEEE",
+        ].join("\n")).unwrap();
+        let start = Game::parse(board, Human);
+
+        let mut bot = Bot::new(1);
+        let analyses = analyze(&mut bot, &start, None);
+        let worst = analyses.last().unwrap().position.clone();
+        assert!(review(&mut bot, &start, &[Position::new(0, 0)], None).is_err());
+
+        let report = review(&mut bot, &start, &[worst], None).unwrap();
+        let played = &report.moves[0];
+        assert_eq!(played.player, Human);
+        assert_eq!(played.best_move, analyses[0].position);
+        assert_eq!(played.evaluation_before, analyses[0].evaluation);
+        assert!(played.loss > 0.0);
+        assert_ne!(played.classification, Classification::Best);
+        assert_eq!(report.players.len(), 1);
+        assert!(report.players[0].accuracy < 100.0);
+
+        let report = review(&mut bot, &start, &[analyses[0].position.clone()], None).unwrap();
+        assert_eq!(report.moves[0].classification, Classification::Best);
+        assert_eq!(report.players[0].accuracy, 100.0);
+        assert_eq!(report.players[0].best, 1);
+    }
+
+    #[test]
+    fn review_midgame() {
+        // Flipping the light disk next to the top left corner opens the corner to light later on
+        let board = Board::parse([
+            "EDDDDDDE",
+            "ELLLLLLE",
+            "EEEEEEEE",
+            "EEEDLEEE",
+            "EEDLDLEE",
+            "EEDLLDEE",
+            "EEDDLDEE",
+            "EEELDEEE",
+        ].join("\n")).unwrap();
+        let start = Game::parse(board, Human);
+        assert_eq!(start.phase(), Phase::Mid);
+
+        let mut bot = Bot::new(2);
+        let report = review(&mut bot, &start, &[Position::new(2, 1)], None).unwrap();
+        let played = &report.moves[0];
+        assert!(played.loss > GOOD_LOSS);
+        assert!(matches!(
+            played.classification,
+            Classification::Inaccuracy | Classification::Mistake | Classification::Blunder
+        ));
+        assert!(report.players[0].accuracy < 100.0);
+    }
+}
@@ -0,0 +1,712 @@
+use rocket::http::Status;
+use rocket::response::Responder;
+use rocket::serde::json::Json;
+use rocket::{Catcher, Request, Route, State};
+use rocket_okapi::gen::OpenApiGenerator;
+use rocket_okapi::okapi::openapi3::{OpenApi, Responses};
+use rocket_okapi::response::OpenApiResponderInner;
+use rocket_okapi::settings::OpenApiSettings;
+use rocket_okapi::util::add_schema_response;
+use rocket_okapi::{openapi, openapi_get_routes_spec};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::{GameReview, MoveAnalysis};
+use crate::board::{Board, DEFAULT_BOARD_SIZE, Position};
+use crate::bot::Mistakes;
+use crate::calibration::{calibration, Prediction};
+use crate::difficulty::Difficulty;
+use crate::engine::Engine;
+use crate::errors::Error;
+use crate::errors::Error::InvalidArgument;
+use crate::game::{Action, EvaluationBreakdown, Game, Handicap, MoveOutcome, normalize, Player, Variant};
+use crate::openings::Start;
+use crate::rolit::{Colour, RolitBoard, RolitGame};
+
+/// Base path of the JSON API
+pub const BASE: &str = "/api/v2";
+
+pub type ApiResult<T> = Result<Json<T>, ApiError>;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+/// Error of the JSON API, sent to the client as an ErrorResponse
+#[derive(Debug)]
+pub struct ApiError {
+    status: Status,
+    error: String,
+}
+
+impl ApiError {
+
+    /// Creates a new error with the given status and message
+    pub fn new(status: Status, message: &str) -> Self {
+        Self {
+            status,
+            error: message.to_string(),
+        }
+    }
+
+    /// Creates a bad request error with the given message
+    pub fn bad_request(message: &str) -> Self {
+        Self::new(Status::BadRequest, message)
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ApiError {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'static> {
+        (self.status, Json(ErrorResponse { error: self.error })).respond_to(request)
+    }
+}
+
+impl OpenApiResponderInner for ApiError {
+    fn responses(gen: &mut OpenApiGenerator) -> rocket_okapi::Result<Responses> {
+        let mut responses = Responses::default();
+        let schema = gen.json_schema::<ErrorResponse>();
+
+        // 422 is sent by the catcher when the body isn't valid JSON for the route
+        add_schema_response(&mut responses, 400, "application/json", schema.clone())?;
+        add_schema_response(&mut responses, 404, "application/json", schema.clone())?;
+        add_schema_response(&mut responses, 422, "application/json", schema)?;
+        Ok(responses)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct GameRequest {
+    pub board: Board,
+    pub player: Player,
+    #[serde(default)]
+    pub variant: Variant,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ResultRequest {
+    pub board: Board,
+    pub player: Player,
+    pub position: Position,
+    #[serde(default)]
+    pub variant: Variant,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct DecideRequest {
+    /// Board to decide on, such as the initial board with a handicap
+    #[serde(flatten)]
+    pub setup: BoardSetup,
+    pub intelligence: Option<u32>,
+    /// Estimated Elo rating to play at when no intelligence is given
+    pub elo: Option<f64>,
+    /// Mistakes to make for a more human play, instead of always playing the best move found
+    pub mistakes: Option<Mistakes>,
+    #[serde(default)]
+    pub variant: Variant,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct AnalyzeRequest {
+    pub board: Board,
+    pub player: Player,
+    pub intelligence: Option<u32>,
+    #[serde(default)]
+    pub variant: Variant,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ReviewRequest {
+    /// Board the game started from
+    #[serde(flatten)]
+    pub setup: BoardSetup,
+    /// Player who moved first, which is the human by default
+    pub first: Option<Player>,
+    /// Positions of the moves in the order they were played, without passes
+    pub moves: Vec<Position>,
+    pub intelligence: Option<u32>,
+    #[serde(default)]
+    pub variant: Variant,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct RolitDecideRequest {
+    pub board: RolitBoard,
+    /// Number of players, who play red, green, yellow and blue in this order
+    pub players: usize,
+    /// Colour of the player to move, for whom the bot decides
+    pub colour: Colour,
+    pub intelligence: Option<u32>,
+}
+
+/// Board to start a game from, which is the initial board by default
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BoardSetup {
+    /// Any board where a move can still be played
+    pub board: Option<Board>,
+    /// Size of the initial board when no board is given, which is 8 by default
+    pub size: Option<usize>,
+    /// Starting position of the initial board when no board is given
+    pub start: Option<Start>,
+    /// Corners given to the weaker side on the initial board when no board is given
+    pub handicap: Option<Handicap>,
+    /// Number of squares of the initial board blocked at random when no board is given
+    pub holes: Option<usize>,
+    /// Seed choosing the opening and the blocked squares, which is random by default
+    pub seed: Option<u64>,
+}
+
+impl BoardSetup {
+
+    /// Returns the given board, or the initial board of the given size, start and holes when there is none
+    pub fn build(self) -> Result<Board, Error> {
+        let given = self.board.is_some();
+        let board = self.build_any()?;
+
+        if given && Game::parse(board.clone(), Player::default()).is_over() {
+            return Err(InvalidArgument("No move can be played on the given board".to_string()));
+        }
+        Ok(board)
+    }
+
+    /// Returns the board like build, even a given one where no move can be played
+    pub fn build_any(self) -> Result<Board, Error> {
+        let Some(board) = self.board else {
+            let seed = self.seed.unwrap_or_else(rand::random);
+            let mut board = self.start.unwrap_or_default().board(self.size.unwrap_or(DEFAULT_BOARD_SIZE), seed)?;
+            if let Some(handicap) = self.handicap {
+                handicap.apply(&mut board)?;
+            }
+            if let Some(holes) = self.holes {
+                board.block_random(holes, seed)?;
+            }
+            return Ok(board);
+        };
+
+        if self.size.is_some_and(|size| size != board.size()) {
+            return Err(InvalidArgument("The size doesn't match the size of the board".to_string()));
+        }
+        if self.start.is_some() || self.handicap.is_some() || self.holes.is_some() {
+            return Err(InvalidArgument("A start, a handicap or holes can't be set up on a given board".to_string()));
+        }
+        Ok(board)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct EvaluateRequest {
+    pub board: Board,
+    #[serde(default)]
+    pub variant: Variant,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GameResponse {
+    pub board: Board,
+    pub current_player: Player,
+    pub variant: Variant,
+    pub is_over: bool,
+    pub winner: Option<Player>,
+}
+
+impl From<&Game> for GameResponse {
+    fn from(game: &Game) -> Self {
+        Self {
+            board: game.board().clone(),
+            current_player: game.current_player(),
+            variant: game.variant(),
+            is_over: game.is_over(),
+            winner: if game.is_over() { game.winner() } else { None },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct RolitGameResponse {
+    pub board: RolitBoard,
+    pub current: Colour,
+    pub is_over: bool,
+    /// Players with the most balls once the game is over
+    pub winners: Vec<Colour>,
+}
+
+impl From<&RolitGame> for RolitGameResponse {
+    fn from(game: &RolitGame) -> Self {
+        Self {
+            board: game.board().clone(),
+            current: game.current(),
+            is_over: game.is_over(),
+            winners: if game.is_over() { game.winners() } else { Vec::new() },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ActionsResponse {
+    pub actions: Vec<Position>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct MoveResponse {
+    pub outcome: MoveOutcome,
+    pub game: GameResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct DecideResponse {
+    pub decision: Option<Action>,
+    pub outcome: Option<MoveOutcome>,
+    pub result: GameResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct RolitDecideResponse {
+    /// Position of the ball placed by the bot, which is null when the game is over
+    pub decision: Option<Position>,
+    pub result: RolitGameResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct AnalyzeResponse {
+    /// Legal moves of the player, best first
+    pub moves: Vec<MoveAnalysis>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct EvaluateResponse {
+    /// Evaluation mapped linearly into [0, 1], where 1 is the best for the bot
+    pub evaluation: f32,
+    /// Evaluation as computed by the engine
+    pub score: i32,
+    /// Chances of each outcome for the bot, with its expected final disk margin
+    pub prediction: Prediction,
+}
+
+/// Returns the board at the start of a game, of size 8 with the diagonal start and without holes by default
+///
+/// The opening of the XOT start and the given number of holes are chosen at random from the seed.
+/// A handicap gives the given number of corners to the side, which is the human by default,
+/// or blocks them when `handicap_blocked` is true.
+#[openapi(tag = "v2")]
+#[get("/initial-board?<size>&<start>&<handicap>&<handicap_side>&<handicap_blocked>&<holes>&<seed>")]
+#[allow(clippy::too_many_arguments)]
+fn initial_board(size: Option<usize>, start: Option<Start>, handicap: Option<usize>, handicap_side: Option<String>,
+                 handicap_blocked: Option<bool>, holes: Option<usize>, seed: Option<u64>) -> ApiResult<Board> {
+    let side = match handicap_side.and_then(|side| side.chars().next()) {
+        Some(ch) => Player::parse(ch).map_err(|e| ApiError::bad_request(&e.to_string()))?,
+        None => Player::Human,
+    };
+    let handicap = handicap.map(|corners| Handicap { side, corners, blocked: handicap_blocked.unwrap_or(false) });
+
+    BoardSetup { board: None, size, start, handicap, holes, seed }.build()
+        .map(Json)
+        .map_err(|e| ApiError::bad_request(&e.to_string()))
+}
+
+/// Evaluates the board for the bot, along with its chances of winning
+#[openapi(tag = "v2")]
+#[post("/evaluate", data = "<request>")]
+fn evaluate(request: Json<EvaluateRequest>) -> ApiResult<EvaluateResponse> {
+    let EvaluateRequest { board, variant } = request.into_inner();
+    let game = Game::parse(board, Player::default()).with_variant(variant);
+    let score = game.evaluate();
+
+    Ok(Json(EvaluateResponse {
+        evaluation: normalize(score, game.board().size()),
+        score,
+        prediction: calibration().predict(&game),
+    }))
+}
+
+/// Explains the evaluation of the board term by term and square by square
+#[openapi(tag = "v2")]
+#[post("/explain", data = "<request>")]
+fn explain(request: Json<EvaluateRequest>) -> ApiResult<EvaluationBreakdown> {
+    let EvaluateRequest { board, variant } = request.into_inner();
+    let game = Game::parse(board, Player::default()).with_variant(variant);
+
+    Ok(Json(game.breakdown()))
+}
+
+/// Returns the positions the player can place a disk at
+#[openapi(tag = "v2")]
+#[post("/actions", data = "<request>")]
+fn actions(request: Json<GameRequest>) -> ApiResult<ActionsResponse> {
+    let GameRequest { board, player, variant } = request.into_inner();
+    let game = Game::parse(board, player).with_variant(variant);
+
+    let mut actions: Vec<Position> = game.actions(player)
+        .map(|action| action.placement().clone())
+        .collect();
+    actions.sort();
+
+    Ok(Json(ActionsResponse { actions }))
+}
+
+/// Places a disk of the player at the position and returns the resulting game with the flipped disks
+#[openapi(tag = "v2")]
+#[post("/result", data = "<request>")]
+fn result(request: Json<ResultRequest>) -> ApiResult<MoveResponse> {
+    let ResultRequest { board, player, position, variant } = request.into_inner();
+    let game = Game::parse(board, player).with_variant(variant);
+    let action = Action::parse(player, position);
+
+    if !game.actions(player).any(|act| act == action) {
+        return Err(ApiError::bad_request("Invalid action for the given player"));
+    }
+
+    let (game, outcome) = game.result_with_outcome(&action);
+    Ok(Json(MoveResponse { outcome, game: GameResponse::from(&game) }))
+}
+
+/// Decides the next action of the bot, which is null when the bot has no action
+#[openapi(tag = "v2")]
+#[post("/decide", data = "<request>")]
+async fn decide(request: Json<DecideRequest>, engine: &State<Engine>) -> ApiResult<DecideResponse> {
+    let DecideRequest { setup, intelligence, elo, mistakes, variant } = request.into_inner();
+    // A finished game is answered with no decision rather than rejected
+    let board = setup.build_any().map_err(|e| ApiError::bad_request(&e.to_string()))?;
+    let game = Game::parse(board, Player::Bot).with_variant(variant);
+    let intelligence = engine.intelligence_for(intelligence, elo);
+    if let Some(mistakes) = &mistakes {
+        mistakes.validate().map_err(|e| ApiError::bad_request(&e.to_string()))?;
+    }
+
+    let response = match engine.decide(game.clone(), Some(intelligence), mistakes).await {
+        Ok((action, _)) => {
+            let (result, outcome) = game.result_with_outcome(&action);
+            DecideResponse {
+                decision: Some(action),
+                outcome: Some(outcome),
+                result: GameResponse::from(&result),
+            }
+        },
+        // No available actions
+        Err(_) => DecideResponse {
+            decision: None,
+            outcome: None,
+            result: GameResponse::from(&game),
+        },
+    };
+
+    Ok(Json(response))
+}
+
+/// Scores every legal move of the player for hints and feedback on their moves
+#[openapi(tag = "v2")]
+#[post("/analyze", data = "<request>")]
+async fn analyze(request: Json<AnalyzeRequest>, engine: &State<Engine>) -> ApiResult<AnalyzeResponse> {
+    let AnalyzeRequest { board, player, intelligence, variant } = request.into_inner();
+    let game = Game::parse(board, player).with_variant(variant);
+
+    Ok(Json(AnalyzeResponse { moves: engine.analyze(game, intelligence).await }))
+}
+
+/// Reviews every move of a finished game, classifying them by how much they lost versus the best move
+#[openapi(tag = "v2")]
+#[post("/review", data = "<request>")]
+async fn review(request: Json<ReviewRequest>, engine: &State<Engine>) -> ApiResult<GameReview> {
+    let ReviewRequest { setup, first, moves, intelligence, variant } = request.into_inner();
+    let board = setup.build().map_err(|e| ApiError::bad_request(&e.to_string()))?;
+    let start = Game::parse(board, first.unwrap_or(Player::Human)).with_variant(variant);
+
+    engine.review(start, moves, intelligence).await
+        .map(Json)
+        .map_err(|e| ApiError::bad_request(&e.to_string()))
+}
+
+/// Returns the estimated Elo rating of the bot at every intelligence requests can ask for
+#[openapi(tag = "v2")]
+#[get("/difficulties")]
+fn difficulties(engine: &State<Engine>) -> ApiResult<Vec<Difficulty>> {
+    Ok(Json(engine.difficulties()))
+}
+
+/// Returns the board at the start of a game of Rolit, of size 8 by default
+#[openapi(tag = "v2")]
+#[get("/rolit/initial-board?<size>")]
+fn rolit_initial_board(size: Option<usize>) -> ApiResult<RolitBoard> {
+    RolitBoard::with_size(size.unwrap_or(DEFAULT_BOARD_SIZE))
+        .map(Json)
+        .map_err(|e| ApiError::bad_request(&e.to_string()))
+}
+
+/// Decides where the player to move in a game of Rolit places a ball, with every other player against it
+#[openapi(tag = "v2")]
+#[post("/rolit/decide", data = "<request>")]
+async fn rolit_decide(request: Json<RolitDecideRequest>, engine: &State<Engine>) -> ApiResult<RolitDecideResponse> {
+    let RolitDecideRequest { board, players, colour, intelligence } = request.into_inner();
+    let game = RolitGame::new(board, players, colour).map_err(|e| ApiError::bad_request(&e.to_string()))?;
+
+    let decision = engine.decide_rolit(game.clone(), intelligence).await;
+    let result = match &decision {
+        Some(placement) => game.result(placement).map_err(|e| ApiError::bad_request(&e.to_string()))?,
+        None => game,
+    };
+    Ok(Json(RolitDecideResponse { decision, result: RolitGameResponse::from(&result) }))
+}
+
+#[catch(default)]
+fn default_catcher(status: Status, _request: &Request) -> (Status, Json<ErrorResponse>) {
+    let error = status.reason().unwrap_or("Unknown error").to_string();
+    (status, Json(ErrorResponse { error }))
+}
+
+/// Returns the routes of the JSON API with their OpenAPI specification
+pub fn routes(settings: &OpenApiSettings) -> (Vec<Route>, OpenApi) {
+    let settings = settings.clone();
+    openapi_get_routes_spec![settings: initial_board, evaluate, explain, actions, result, decide, analyze,
+                                             review, difficulties, rolit_initial_board, rolit_decide]
+}
+
+/// Returns the catchers that report errors of the JSON API as JSON
+pub fn catchers() -> Vec<Catcher> {
+    catchers![default_catcher]
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::http::{ContentType, Status};
+    use rocket::local::blocking::Client;
+    use rocket_okapi::settings::OpenApiSettings;
+    use serde_json::json;
+
+    use crate::api::{ActionsResponse, AnalyzeResponse, BASE, catchers, DecideResponse, ErrorResponse, MoveResponse,
+                     RolitDecideResponse, routes};
+    use crate::analysis::GameReview;
+    use crate::board::{Board, Position};
+    use crate::difficulty::Difficulty;
+    use crate::engine::Engine;
+    use crate::game::Player::{Bot, Human};
+    use crate::game::Variant;
+    use crate::rolit::{Colour, RolitBoard};
+    use crate::settings::EngineSettings;
+
+    fn client() -> Client {
+        let rocket = rocket::build()
+            .mount(BASE, routes(&OpenApiSettings::default()).0)
+            .register(BASE, catchers())
+            .manage(Engine::new(EngineSettings::default()).unwrap());
+
+        Client::tracked(rocket).unwrap()
+    }
+
+    #[test]
+    fn actions() {
+        let client = client();
+
+        let response = client.post(format!("{}/actions", BASE))
+            .header(ContentType::JSON)
+            .body(json!({ "board": Board::new(), "player": "H" }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let actions = response.into_json::<ActionsResponse>().unwrap().actions;
+        assert_eq!(actions, vec![Position::new(2, 4), Position::new(3, 5), Position::new(4, 2), Position::new(5, 3)]);
+
+        let response = client.post(format!("{}/actions", BASE))
+            .header(ContentType::JSON)
+            .body(json!({ "board": ["EEEE"], "player": "H" }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        assert!(response.into_json::<ErrorResponse>().is_some());
+    }
+
+    #[test]
+    fn result() {
+        let client = client();
+
+        let response = client.post(format!("{}/result", BASE))
+            .header(ContentType::JSON)
+            .body(json!({ "board": Board::new(), "player": "H", "position": { "row": 2, "col": 4 } }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let MoveResponse { outcome, game } = response.into_json::<MoveResponse>().unwrap();
+        assert_eq!(outcome.flipped_positions().collect::<Vec<_>>(), vec![&Position::new(3, 4)]);
+        assert_eq!(outcome.disks_after.dark, outcome.disks_before.dark + 2);
+        assert_eq!(game.current_player, Bot);
+        assert!(!game.is_over);
+        assert_eq!(game.board.disk(&Position::new(3, 4)), Some(Human.disk()));
+
+        let response = client.post(format!("{}/result", BASE))
+            .header(ContentType::JSON)
+            .body(json!({ "board": Board::new(), "player": "H", "position": { "row": 0, "col": 0 } }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn decide() {
+        let client = client();
+
+        let response = client.post(format!("{}/decide", BASE))
+            .header(ContentType::JSON)
+            .body(json!({ "board": Board::new(), "intelligence": 1 }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let decision = response.into_json::<DecideResponse>().unwrap();
+        let action = decision.decision.unwrap();
+        assert_eq!(action.player(), Bot);
+        assert_eq!(&decision.outcome.unwrap().placement, action.placement());
+        assert_eq!(decision.result.board.disk(action.placement()), Some(Bot.disk()));
+        assert_eq!(decision.result.variant, Variant::Standard);
+
+        let response = client.post(format!("{}/decide", BASE))
+            .header(ContentType::JSON)
+            .body(json!({ "board": Board::new(), "intelligence": 1, "variant": "anti" }).to_string())
+            .dispatch();
+        let decision = response.into_json::<DecideResponse>().unwrap();
+        assert_eq!(decision.result.variant, Variant::Anti);
+
+        // The bot plays against a handicap set up on the initial board, but not on a given one
+        let handicap = json!({ "side": "H", "corners": 2 });
+        let response = client.post(format!("{}/decide", BASE))
+            .header(ContentType::JSON)
+            .body(json!({ "handicap": handicap, "intelligence": 1 }).to_string())
+            .dispatch();
+        let decision = response.into_json::<DecideResponse>().unwrap();
+        assert!(decision.decision.is_some());
+        assert_eq!(decision.result.board.disk(&Position::new(0, 0)), Some(Human.disk()));
+        assert_eq!(decision.result.board.disk(&Position::new(7, 7)), Some(Human.disk()));
+
+        let response = client.post(format!("{}/decide", BASE))
+            .header(ContentType::JSON)
+            .body(json!({ "board": Board::new(), "handicap": handicap }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let response = client.post(format!("{}/decide", BASE))
+            .header(ContentType::JSON)
+            .body(json!({ "board": Board::new(), "elo": 1000 }).to_string())
+            .dispatch();
+        assert!(response.into_json::<DecideResponse>().unwrap().decision.is_some());
+
+        // The same mistakes play the same move
+        let decide = |mistakes: serde_json::Value| client.post(format!("{}/decide", BASE))
+            .header(ContentType::JSON)
+            .body(json!({ "board": Board::new(), "intelligence": 2, "mistakes": mistakes }).to_string())
+            .dispatch();
+        let mistakes = json!({ "temperature": 50.0, "oversight": 0.3, "horizon": 1, "seed": 7 });
+        let first = decide(mistakes.clone()).into_json::<DecideResponse>().unwrap().decision;
+        assert!(first.is_some());
+        assert_eq!(decide(mistakes).into_json::<DecideResponse>().unwrap().decision, first);
+        assert_eq!(decide(json!({ "temperature": -1.0 })).status(), Status::BadRequest);
+
+        let response = client.get(format!("{}/difficulties", BASE)).dispatch();
+        let difficulties = response.into_json::<Vec<Difficulty>>().unwrap();
+        assert_eq!(difficulties.len(), 9);
+        assert!(difficulties[8].elo > difficulties[0].elo);
+    }
+
+    #[test]
+    fn rolit() {
+        let client = client();
+
+        let response = client.get(format!("{}/rolit/initial-board?size=6", BASE)).dispatch();
+        let board = response.into_json::<RolitBoard>().unwrap();
+        assert_eq!(board.size(), 6);
+
+        let response = client.post(format!("{}/rolit/decide", BASE))
+            .header(ContentType::JSON)
+            .body(json!({ "board": board, "players": 3, "colour": "G", "intelligence": 1 }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let decision = response.into_json::<RolitDecideResponse>().unwrap();
+        let placement = decision.decision.unwrap();
+        assert_eq!(decision.result.board.ball(&placement), Some(Colour::Green));
+        assert_eq!(decision.result.current, Colour::Yellow);
+
+        let response = client.post(format!("{}/rolit/decide", BASE))
+            .header(ContentType::JSON)
+            .body(json!({ "board": board, "players": 2, "colour": "B" }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn board_sizes() {
+        let client = client();
+
+        let response = client.get(format!("{}/initial-board?size=6", BASE)).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let board = response.into_json::<Board>().unwrap();
+        assert_eq!(board.size(), 6);
+
+        let response = client.get(format!("{}/initial-board?size=5", BASE)).dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let response = client.get(format!("{}/initial-board?holes=4&seed=7", BASE)).dispatch();
+        let board = response.into_json::<Board>().unwrap();
+        assert_eq!(board.blocked_positions().count(), 4);
+        assert_eq!(board, Board::with_holes(8, 4, 7).unwrap());
+
+        let response = client.get(format!("{}/initial-board?holes=60", BASE)).dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let response = client.get(format!("{}/initial-board?size=4&start=straight", BASE)).dispatch();
+        let board = response.into_json::<Board>().unwrap();
+        assert_eq!(board.to_string(), "EEEE\nEDDE\nELLE\nEEEE");
+
+        let response = client.get(format!("{}/initial-board?handicap=2&handicap_side=B", BASE)).dispatch();
+        let board = response.into_json::<Board>().unwrap();
+        assert_eq!(board.disk(&Position::new(7, 7)), Some(Bot.disk()));
+        assert_eq!(board.disk(&Position::new(0, 7)), None);
+
+        let response = client.get(format!("{}/initial-board?handicap=5", BASE)).dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let response = client.get(format!("{}/initial-board?start=xot&seed=5", BASE)).dispatch();
+        let board = response.into_json::<Board>().unwrap();
+        assert_eq!(board.empty_positions().count(), 52);
+
+        let response = client.post(format!("{}/decide", BASE))
+            .header(ContentType::JSON)
+            .body(json!({ "board": Board::with_size(10).unwrap(), "intelligence": 1 }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let decision = response.into_json::<DecideResponse>().unwrap();
+        assert_eq!(decision.result.board.size(), 10);
+    }
+
+    #[test]
+    fn analyze() {
+        let client = client();
+
+        let response = client.post(format!("{}/analyze", BASE))
+            .header(ContentType::JSON)
+            .body(json!({ "board": Board::new(), "player": "H", "intelligence": 1 }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let moves = response.into_json::<AnalyzeResponse>().unwrap().moves;
+        assert_eq!(moves.len(), 4);
+        assert!(moves[0].best);
+        assert_eq!(moves[0].pv[0], moves[0].position);
+    }
+
+    #[test]
+    fn review() {
+        let client = client();
+
+        let response = client.post(format!("{}/review", BASE))
+            .header(ContentType::JSON)
+            .body(json!({ "moves": [{ "row": 2, "col": 4 }, { "row": 2, "col": 3 }], "intelligence": 1 }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let review = response.into_json::<GameReview>().unwrap();
+        assert_eq!(review.moves.len(), 2);
+        assert_eq!(review.moves[0].player, Human);
+        assert_eq!(review.moves[1].player, Bot);
+        assert_eq!(review.players.len(), 2);
+
+        let response = client.post(format!("{}/review", BASE))
+            .header(ContentType::JSON)
+            .body(json!({ "moves": [{ "row": 0, "col": 0 }] }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+}
@@ -0,0 +1,303 @@
+use std::{env, fs};
+use std::io;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use desdemona::board::{Board, DEFAULT_BOARD_SIZE, Disk, Position};
+use desdemona::bot::Bot;
+use desdemona::errors::Error;
+use desdemona::errors::Error::{InvalidArgument, ParseError};
+use desdemona::game::{Action, Game, Player, Variant};
+
+const DEFAULT_INTELLIGENCE: u32 = 3;
+
+const USAGE: &str = "Usage: desdemona-cli [--intelligence <n>] [--size <n>] [--variant standard|anti] [--load <file>]";
+
+const HELP: &str = "\
+Commands:
+  <move>         Place a disk, e.g. e3
+  undo           Take back your last move and the replies of the bot
+  save <file>    Save the game to the file
+  load <file>    Load a game saved to the file
+  help           Show this help
+  quit           Leave the game";
+
+/// Game written to a file as the moves played from its start
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SavedGame {
+    start: Board,
+    first: Player,
+    #[serde(default)]
+    variant: Variant,
+    moves: Vec<Position>,
+}
+
+/// Game of the terminal, with the actions played so far and the states before each of them
+struct Session {
+    start: Game,
+    game: Game,
+    history: Vec<(Game, Action)>,
+}
+
+impl Session {
+
+    /// Creates a new session from the given start
+    fn new(start: Game) -> Self {
+        Self { game: start.clone(), start, history: Vec::new() }
+    }
+
+    /// Plays the given action, passing the turn first when the player to move has no actions
+    fn play(&mut self, position: Position) -> Result<(), Error> {
+        self.pass_if_stuck();
+
+        let action = Action::parse(self.game.current_player(), position);
+        if self.game.is_over() || !self.game.actions(action.player()).any(|act| act == action) {
+            return Err(InvalidArgument(format!("{} is not a legal move", action.placement().to_algebraic())));
+        }
+
+        let result = self.game.result(&action);
+        self.history.push((self.game.clone(), action));
+        self.game = result;
+        Ok(())
+    }
+
+    /// Passes the turn when the player to move has no actions but the game isn't over
+    fn pass_if_stuck(&mut self) {
+        if !self.game.is_over() && self.game.actions(self.game.current_player()).next().is_none() {
+            self.game = self.game.pass();
+        }
+    }
+
+    /// Takes back the last move of the human along with the moves of the bot after it
+    fn undo(&mut self) -> Result<(), Error> {
+        if !self.history.iter().any(|(_, action)| action.player() == Player::Human) {
+            return Err(InvalidArgument("There is no move to undo".to_string()));
+        }
+
+        while let Some((game, action)) = self.history.pop() {
+            self.game = game;
+            if action.player() == Player::Human {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the game to write to a file
+    fn save(&self) -> SavedGame {
+        SavedGame {
+            start: self.start.board().clone(),
+            first: self.start.current_player(),
+            variant: self.start.variant(),
+            moves: self.history.iter().map(|(_, action)| action.placement().clone()).collect(),
+        }
+    }
+
+    /// Replays the moves of the given saved game from its start
+    fn load(saved: SavedGame) -> Result<Self, Error> {
+        let mut session = Self::new(Game::parse(saved.start, saved.first).with_variant(saved.variant));
+        for position in saved.moves {
+            session.play(position)?;
+        }
+        Ok(session)
+    }
+}
+
+/// Renders the board with its coordinates, marking the given legal moves.
+/// Disks are drawn in colour on a green board unless `colour` is false.
+fn render(board: &Board, legal: &[Position], colour: bool) -> String {
+    let size = board.size();
+    let letters = (0..size).map(|col| format!(" {}", (b'a' + col as u8) as char)).collect::<String>();
+    let mut out = format!("   {}\n", letters);
+
+    for row in 0..size {
+        out.push_str(&format!("{:>2} ", row + 1));
+        for col in 0..size {
+            let pos = Position::new(row, col);
+            let cell = match board.disk(&pos) {
+                Some(Disk::Dark) if colour => "\x1b[30m●",
+                Some(Disk::Light) if colour => "\x1b[97m●",
+                Some(Disk::Dark) => "D",
+                Some(Disk::Light) => "L",
+                None if board.is_blocked(&pos) => "#",
+                None if legal.contains(&pos) && colour => "\x1b[33m·",
+                None if legal.contains(&pos) => "*",
+                None => ".",
+            };
+            if colour {
+                out.push_str(&format!("\x1b[42m {}\x1b[0m", cell));
+            } else {
+                out.push_str(&format!(" {}", cell));
+            }
+        }
+        out.push_str(&format!(" {}\n", row + 1));
+    }
+
+    out.push_str(&format!("   {}", letters));
+    out
+}
+
+/// Returns the disk counts of both players, with the winner once the game is over
+fn status(game: &Game) -> String {
+    let count = |player: Player| game.board().positions(player.disk()).count();
+    let score = format!("You (dark) {} - {} Bot (light)", count(Player::Human), count(Player::Bot));
+
+    if !game.is_over() {
+        return score;
+    }
+    match game.winner() {
+        Some(Player::Human) => format!("{}. You win!", score),
+        Some(Player::Bot) => format!("{}. The bot wins.", score),
+        None => format!("{}. It's a draw.", score),
+    }
+}
+
+/// Options of the command line
+struct Options {
+    intelligence: u32,
+    size: usize,
+    variant: Variant,
+    load: Option<String>,
+}
+
+impl Options {
+
+    /// Parses the given arguments, without the name of the program
+    fn parse(args: &[String]) -> Result<Self, Error> {
+        let mut options = Self { intelligence: DEFAULT_INTELLIGENCE, size: DEFAULT_BOARD_SIZE,
+                                 variant: Variant::Standard, load: None };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let value = args.next().ok_or_else(|| ParseError(format!("Missing value of {}", arg)))?;
+            let invalid = |_| ParseError(format!("Invalid value of {}: {}", arg, value));
+            match arg.as_str() {
+                "--intelligence" => options.intelligence = value.parse().map_err(invalid)?,
+                "--size" => options.size = value.parse().map_err(invalid)?,
+                "--variant" => options.variant = match value.as_str() {
+                    "standard" => Variant::Standard,
+                    "anti" => Variant::Anti,
+                    _ => return Err(ParseError(format!("Invalid value of {}: {}", arg, value))),
+                },
+                "--load" => options.load = Some(value.clone()),
+                _ => return Err(ParseError(format!("Unknown option: {}", arg))),
+            }
+        }
+        Ok(options)
+    }
+}
+
+fn load(path: &str) -> Result<Session, Box<dyn std::error::Error>> {
+    let saved = serde_json::from_str(&fs::read_to_string(Path::new(path))?)?;
+    Ok(Session::load(saved)?)
+}
+
+fn save(session: &Session, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    fs::write(path, serde_json::to_string_pretty(&session.save())?)?;
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let options = Options::parse(&args).map_err(|e| format!("{}\n{}", e, USAGE))?;
+    let colour = env::var_os("NO_COLOR").is_none();
+
+    let mut session = match &options.load {
+        Some(path) => load(path)?,
+        None => Session::new(Game::parse(Board::with_size(options.size)?, Player::Human)
+            .with_variant(options.variant)),
+    };
+    let mut bot = Bot::new(options.intelligence);
+
+    println!("{}", HELP);
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        session.pass_if_stuck();
+        if session.game.current_player() == Player::Bot && !session.game.is_over() {
+            let (action, _) = bot.decide(&session.game)?;
+            println!("Bot plays {}", action.placement().to_algebraic());
+            session.play(action.placement().clone())?;
+            continue;
+        }
+
+        let legal = session.game.actions(Player::Human).map(|action| action.placement().clone()).collect::<Vec<_>>();
+        println!("\n{}\n{}", render(session.game.board(), &legal, colour), status(&session.game));
+        if session.game.is_over() {
+            println!("Type undo to take back your last move or quit to leave");
+        }
+
+        print!("> ");
+        io::stdout().flush()?;
+        let Some(line) = lines.next() else {
+            return Ok(());
+        };
+        let line = line?;
+        let mut words = line.split_whitespace();
+
+        let result = match (words.next(), words.next()) {
+            (None, _) => Ok(()),
+            (Some("quit"), _) => return Ok(()),
+            (Some("help"), _) => {
+                println!("{}", HELP);
+                Ok(())
+            },
+            (Some("undo"), _) => session.undo().map_err(|e| e.into()),
+            (Some("save"), Some(path)) => save(&session, path).map(|_| println!("Saved the game to {}", path)),
+            (Some("load"), Some(path)) => load(path).map(|loaded| session = loaded),
+            (Some(word), _) => Position::parse_algebraic(word)
+                .and_then(|position| session.play(position))
+                .map_err(|e| e.into()),
+        };
+
+        if let Err(e) = result {
+            println!("{}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use desdemona::board::{Board, Position};
+    use desdemona::game::{Game, Player};
+
+    use crate::{render, Session};
+
+    #[test]
+    fn render_board() {
+        let board = Board::new();
+        let legal = vec![Position::parse_algebraic("d3").unwrap()];
+        let rendered = render(&board, &legal, false);
+
+        let lines = rendered.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0].trim(), "a b c d e f g h");
+        assert_eq!(lines[3], " 3  . . . * . . . . 3");
+        assert_eq!(lines[4], " 4  . . . D L . . . 4");
+        assert!(render(&board, &legal, true).contains("\x1b[42m"));
+    }
+
+    #[test]
+    fn undo_and_save() {
+        let mut session = Session::new(Game::parse(Board::new(), Player::Human));
+        assert!(session.undo().is_err());
+        assert!(session.play(Position::parse_algebraic("a1").unwrap()).is_err());
+
+        let human = session.game.actions(Player::Human).next().unwrap();
+        session.play(human.placement().clone()).unwrap();
+        let bot = session.game.actions(Player::Bot).next().unwrap();
+        session.play(bot.placement().clone()).unwrap();
+
+        let saved = session.save();
+        assert_eq!(saved.moves.len(), 2);
+        let loaded = Session::load(saved.clone()).unwrap();
+        assert_eq!(loaded.game.board(), session.game.board());
+        assert_eq!(loaded.game.current_player(), Player::Human);
+        assert_eq!(loaded.save(), saved);
+
+        session.undo().unwrap();
+        assert_eq!(session.game.board(), &Board::new());
+        assert_eq!(session.game.current_player(), Player::Human);
+        assert!(session.history.is_empty());
+    }
+}
@@ -0,0 +1,366 @@
+use std::io;
+use std::io::{BufRead, Write};
+use std::time::{Duration, Instant};
+
+use itertools::Itertools;
+
+use desdemona::analysis::analyze;
+use desdemona::board::{Board, check_size, DARK_CHAR, EMPTY_CHAR, LIGHT_CHAR, Position};
+use desdemona::bot::Bot;
+use desdemona::errors::Error;
+use desdemona::errors::Error::{InvalidArgument, ParseError};
+use desdemona::game::{Action, Game, Player};
+
+/// Name the engine reports to the GUI
+const NAME: &str = "Desdemona";
+
+/// Depth searched until the GUI sets one
+const DEFAULT_DEPTH: u32 = 4;
+
+/// Deepest search the engine runs, whatever depth the GUI asks for
+const MAX_DEPTH: u32 = 10;
+
+/// Move of a player without any legal move
+const PASS: &str = "PA";
+
+/// Engine speaking the NBoard protocol
+///
+/// NBoard games start from the usual position where black moves first from d5 and e4. Black plays dark here,
+/// so the columns of every board and move are mirrored onto this board, where dark starts on d4 and e5.
+struct NBoard {
+    game: Option<Game>,
+    depth: u32,
+    /// Time each player has for the whole game
+    time_control: Option<Duration>,
+    /// Time each player spent on its moves so far, dark first
+    spent: [Duration; 2],
+}
+
+impl NBoard {
+
+    fn new() -> Self {
+        Self { game: None, depth: DEFAULT_DEPTH, time_control: None, spent: [Duration::ZERO; 2] }
+    }
+
+    /// Returns the time the given player has left for the rest of the game, if the game is timed
+    fn time_left(&self, player: Player) -> Option<Duration> {
+        self.time_control.map(|time| time.saturating_sub(self.spent[side(player)]))
+    }
+
+    /// Handles a line sent by the GUI and returns the lines to reply with
+    fn handle(&mut self, line: &str) -> Result<Vec<String>, Error> {
+        let (command, args) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+
+        match command {
+            "nboard" => Ok(vec![format!("set myname {}", NAME)]),
+            "ping" => Ok(vec![format!("pong {}", args.trim())]),
+            "set" => self.set(args),
+            "move" => {
+                self.play(args)?;
+                Ok(Vec::new())
+            },
+            "go" => self.go(),
+            "hint" => self.hint(args.trim().parse().map_err(|_| ParseError(format!("Invalid hint count: {}", args)))?),
+            // Nothing is learned from finished games, but the GUI waits for the reply
+            "learn" => Ok(vec!["learned".to_string()]),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Handles the settings of the game and the search
+    fn set(&mut self, args: &str) -> Result<Vec<String>, Error> {
+        let (name, value) = args.trim().split_once(' ').unwrap_or((args.trim(), ""));
+
+        match name {
+            "depth" => {
+                let depth = value.trim().parse::<u32>().map_err(|_| ParseError(format!("Invalid depth: {}", value)))?;
+                self.depth = depth.clamp(1, MAX_DEPTH);
+            },
+            "game" => {
+                let (game, time_control, spent) = parse_ggf(value)?;
+                self.game = Some(game);
+                self.time_control = time_control;
+                self.spent = spent;
+            },
+            _ => {},
+        }
+        Ok(Vec::new())
+    }
+
+    /// Plays the given move of the player to move, in NBoard coordinates with the optional evaluation and time
+    fn play(&mut self, mv: &str) -> Result<(), Error> {
+        let game = self.game.as_ref().ok_or_else(|| InvalidArgument("No game is set".to_string()))?;
+        let (notation, time) = split_move(mv)?;
+
+        let player = game.current_player();
+        self.game = Some(play(game, notation)?);
+        self.spent[side(player)] += time.unwrap_or_default();
+        Ok(())
+    }
+
+    /// Decides the move of the player to move and plays it
+    fn go(&mut self) -> Result<Vec<String>, Error> {
+        let game = self.game.as_ref().ok_or_else(|| InvalidArgument("No game is set".to_string()))?;
+        let start = Instant::now();
+
+        let notation = if game.actions(game.current_player()).next().is_none() {
+            PASS.to_string()
+        } else {
+            let mut bot = Bot::new(self.depth);
+            if let Some(time_left) = self.time_left(game.current_player()) {
+                bot.set_deadline(start + time_left / moves_left(game));
+            }
+
+            // The bot always plays light
+            let game = if game.current_player() == Player::Bot { game.clone() } else { game.swap_colours() };
+            let (action, _) = bot.decide_iteratively(&game, |_, _| {})?;
+            to_nboard(action.placement(), game.board().size())
+        };
+
+        let elapsed = start.elapsed();
+        let reply = format!("{}//{:.2}", notation, elapsed.as_secs_f32());
+        self.play(&reply)?;
+        Ok(vec![format!("=== {}", reply)])
+    }
+
+    /// Scores the given number of best moves of the player to move, in disks for that player
+    fn hint(&mut self, count: usize) -> Result<Vec<String>, Error> {
+        let game = self.game.as_ref().ok_or_else(|| InvalidArgument("No game is set".to_string()))?;
+        let size = game.board().size();
+        let mut bot = Bot::new(self.depth);
+
+        let mut lines = vec!["status thinking".to_string()];
+        for analysis in analyze(&mut bot, game, None).into_iter().take(count) {
+            let pv = analysis.pv.iter().map(|position| to_nboard(position, size)).join("");
+            lines.push(format!("search {} {:.1} 0 {}", pv, analysis.expected_margin, self.depth));
+        }
+        lines.push("status".to_string());
+        Ok(lines)
+    }
+}
+
+/// Parses a game in the Generic Game Format, returning the game after its moves along with the time each player has
+/// for the game, if the game has one, and the time each player spent on its moves, dark first
+fn parse_ggf(ggf: &str) -> Result<(Game, Option<Duration>, [Duration; 2]), Error> {
+    let tags = tags(ggf);
+
+    let (_, setup) = tags.iter()
+        .find(|(name, _)| name == "BO")
+        .ok_or_else(|| ParseError("Game has no board".to_string()))?;
+    let mut cells = setup.split_whitespace();
+    let size = cells.next()
+        .and_then(|size| size.parse::<usize>().ok())
+        .ok_or_else(|| ParseError(format!("Invalid board: {}", setup)))?;
+    check_size(size).map_err(|e| ParseError(e.to_string()))?;
+    let mut cells = cells.flat_map(str::chars).collect_vec();
+    let to_move = cells.pop().ok_or_else(|| ParseError(format!("Invalid board: {}", setup)))?;
+    if cells.len() != size * size {
+        return Err(ParseError(format!("Board must have {} cells: {}", size * size, setup)));
+    }
+
+    let rows = cells.chunks(size)
+        .map(|row| row.iter()
+            .rev()
+            .map(|cell| match cell {
+                '*' => Ok(DARK_CHAR),
+                'O' => Ok(LIGHT_CHAR),
+                '-' => Ok(EMPTY_CHAR),
+                _ => Err(ParseError(format!("Invalid cell of the board: {}", cell))),
+            })
+            .collect::<Result<String, Error>>())
+        .collect::<Result<Vec<_>, Error>>()?;
+    let player = match to_move {
+        '*' => Player::Human,
+        'O' => Player::Bot,
+        _ => return Err(ParseError(format!("Invalid side to move: {}", to_move))),
+    };
+
+    let mut game = Game::parse(Board::parse(rows.join("\n"))?, player);
+    let mut spent = [Duration::ZERO; 2];
+    for (_, mv) in tags.iter().filter(|(name, _)| name == "B" || name == "W") {
+        let (notation, time) = split_move(mv)?;
+        spent[side(game.current_player())] += time.unwrap_or_default();
+        game = play(&game, notation)?;
+    }
+
+    let time_control = match tags.iter().find(|(name, _)| name == "TI") {
+        Some((_, time)) => parse_time(time.split('/').next().unwrap_or_default())?,
+        None => None,
+    };
+    Ok((game, time_control, spent))
+}
+
+/// Splits the given move written as "move[/evaluation[/seconds]]" into the move and the time spent on it
+fn split_move(mv: &str) -> Result<(&str, Option<Duration>), Error> {
+    let mut parts = mv.trim().split('/');
+    let notation = parts.next().unwrap_or_default();
+    let time = parts.nth(1).map(parse_time).transpose()?.flatten();
+    Ok((notation, time))
+}
+
+/// Returns the index of the given player among the times of the players, dark first
+fn side(player: Player) -> usize {
+    match player {
+        Player::Human => 0,
+        Player::Bot => 1,
+    }
+}
+
+/// Splits the given GGF game into the names and values of its tags, such as ("BO", "8 ... *")
+fn tags(ggf: &str) -> Vec<(String, String)> {
+    let mut tags = Vec::new();
+    let mut rest = ggf;
+
+    while let Some(open) = rest.find('[') {
+        let name = rest[..open].chars().rev()
+            .take_while(char::is_ascii_uppercase)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .collect::<String>();
+        let Some(close) = rest[open..].find(']') else {
+            break;
+        };
+        tags.push((name, rest[open + 1..open + close].to_string()));
+        rest = &rest[open + close + 1..];
+    }
+
+    tags
+}
+
+/// Parses the given time written as "[[hours:]minutes:]seconds", which is None when the time is left out
+fn parse_time(time: &str) -> Result<Option<Duration>, Error> {
+    if time.trim().is_empty() {
+        return Ok(None);
+    }
+
+    let invalid = || ParseError(format!("Invalid time: {}", time));
+    let seconds = time.split(':')
+        .try_fold(0.0, |total, part| part.trim().parse::<f64>().ok().map(|n| total * 60.0 + n))
+        .ok_or_else(invalid)?;
+    Duration::try_from_secs_f64(seconds).map(Some).map_err(|_| invalid())
+}
+
+/// Returns the game after the player to move plays the given move in NBoard coordinates
+fn play(game: &Game, notation: &str) -> Result<Game, Error> {
+    let player = game.current_player();
+
+    if notation.trim().eq_ignore_ascii_case(PASS) {
+        if game.actions(player).next().is_some() {
+            return Err(InvalidArgument(format!("{} can't pass with legal moves", player)));
+        }
+        return Ok(game.pass());
+    }
+
+    let action = Action::parse(player, from_nboard(notation.trim(), game.board().size())?);
+    if !game.actions(player).any(|act| act == action) {
+        return Err(InvalidArgument(format!("Illegal move {}", notation)));
+    }
+    Ok(game.result(&action))
+}
+
+/// Returns the number of moves the player to move has left to play at most
+fn moves_left(game: &Game) -> u32 {
+    (game.board().empty_positions().count() as u32).div_ceil(2).max(1)
+}
+
+/// Parses the given move in NBoard coordinates, such as "F5", into a position on a board of the given size
+fn from_nboard(notation: &str, size: usize) -> Result<Position, Error> {
+    let position = Position::parse_algebraic(notation)?;
+    if !position.is_inbound(size) {
+        return Err(ParseError(format!("Move is off the board: {}", notation)));
+    }
+    Ok(Position::new(position.row(), size - 1 - position.col()))
+}
+
+/// Returns the NBoard coordinates of the given position on a board of the given size
+fn to_nboard(position: &Position, size: usize) -> String {
+    Position::new(position.row(), size - 1 - position.col()).to_algebraic().to_uppercase()
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut nboard = NBoard::new();
+    let mut stdout = io::stdout();
+
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        match nboard.handle(&line) {
+            Ok(replies) => {
+                for reply in replies {
+                    writeln!(stdout, "{}", reply)?;
+                }
+            },
+            Err(e) => writeln!(stdout, "status {}", e)?,
+        }
+        stdout.flush()?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use desdemona::board::{Board, Position};
+    use desdemona::game::Player;
+
+    use crate::{from_nboard, NBoard, parse_ggf, parse_time, to_nboard};
+
+    const START: &str = "(;GM[Othello]PC[NBoard]PB[Black]PW[White]RE[?]TI[5:00]TY[8]\
+        BO[8 ---------------------------O*------*O--------------------------- *];)";
+
+    #[test]
+    fn ggf() {
+        let (game, time_control, spent) = parse_ggf(START).unwrap();
+        assert_eq!(game.board(), &Board::new());
+        assert_eq!(game.current_player(), Player::Human);
+        assert_eq!(time_control, Some(Duration::from_secs(300)));
+        assert_eq!(spent, [Duration::ZERO; 2]);
+
+        let (game, _, spent) = parse_ggf(&START.replace(";)", "B[F5//1.5]W[f6/0.3/2];)")).unwrap();
+        assert_eq!(game.board().empty_positions().count(), 58);
+        assert_eq!(game.current_player(), Player::Human);
+        assert_eq!(spent, [Duration::from_millis(1500), Duration::from_secs(2)]);
+
+        assert!(parse_ggf(&START.replace(";)", "B[A1];)")).is_err());
+        assert!(parse_ggf("(;GM[Othello];)").is_err());
+        assert!(parse_ggf("(;GM[Othello]BO[0 *];)").is_err());
+        assert!(parse_ggf(&START.replace("TI[5:00]", "TI[-1]")).is_err());
+        assert!(parse_ggf(&START.replace("TI[5:00]", "TI[nan]")).is_err());
+
+        assert_eq!(parse_time("1:02:03.5").unwrap(), Some(Duration::from_secs_f64(3723.5)));
+        assert_eq!(parse_time("").unwrap(), None);
+
+        let position = from_nboard("F5", 8).unwrap();
+        assert_eq!(position, Position::new(4, 2));
+        assert_eq!(to_nboard(&position, 8), "F5");
+    }
+
+    #[test]
+    fn protocol() {
+        let mut nboard = NBoard::new();
+        assert_eq!(nboard.handle("nboard 2").unwrap(), vec!["set myname Desdemona"]);
+        assert_eq!(nboard.handle("ping 3").unwrap(), vec!["pong 3"]);
+        assert!(nboard.handle("go").is_err());
+
+        nboard.handle("set depth 2").unwrap();
+        nboard.handle(&format!("set game {}", START)).unwrap();
+        nboard.handle("move f5/0.00/1.2").unwrap();
+        assert!(nboard.handle("move a1").is_err());
+
+        let hints = nboard.handle("hint 2").unwrap();
+        assert_eq!(hints.len(), 4);
+        assert!(hints[1].starts_with("search "));
+        assert_eq!(hints.last().unwrap(), "status");
+
+        // White replies to the first move of black
+        let replies = nboard.handle("go").unwrap();
+        assert!(["=== D6", "=== F4", "=== F6"].iter().any(|reply| replies[0].starts_with(reply)), "{:?}", replies);
+        assert_eq!(nboard.game.as_ref().unwrap().current_player(), Player::Human);
+        assert!(nboard.time_left(Player::Bot).unwrap() < Duration::from_secs(300));
+        assert_eq!(nboard.time_left(Player::Human), Some(Duration::from_millis(298_800)));
+
+        assert_eq!(nboard.handle("learn").unwrap(), vec!["learned"]);
+    }
+}
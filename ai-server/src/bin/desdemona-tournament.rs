@@ -0,0 +1,121 @@
+use std::{env, thread};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use desdemona::errors::Error;
+use desdemona::errors::Error::ParseError;
+use desdemona::openings::{Openings, set_openings};
+use desdemona::tournament::{EngineConfig, Record, run_tournament, Verdict, Z_95};
+
+const USAGE: &str = "\
+Usage: desdemona-tournament --engine <settings> --engine <settings> [--pairs <n>] [--threads <n>] [--seed <n>]
+                            [--openings <file>] [--output <file>] [--elo0 <elo>] [--elo1 <elo>] [--alpha <p>] [--beta <p>]
+
+Engine settings are comma separated, such as name=new,depth=4,weights=weights.json. The depth is required.
+The tournament stops as soon as the SPRT accepts either hypothesis, or after all pairs otherwise.
+Games are written to the output file as JSON, one game per line.";
+
+/// Options of the command line
+struct Options {
+    engines: Vec<EngineConfig>,
+    pairs: usize,
+    threads: usize,
+    seed: u64,
+    output: String,
+    elo0: f64,
+    elo1: f64,
+    alpha: f64,
+    beta: f64,
+}
+
+impl Options {
+
+    /// Parses the given arguments, without the name of the program
+    fn parse(args: &[String]) -> Result<Self, Error> {
+        let mut options = Self {
+            engines: Vec::new(),
+            pairs: 50,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            seed: rand::random(),
+            output: "tournament.jsonl".to_string(),
+            elo0: 0.0,
+            elo1: 10.0,
+            alpha: 0.05,
+            beta: 0.05,
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let value = args.next().ok_or_else(|| ParseError(format!("Missing value of {}", arg)))?;
+            let invalid = || ParseError(format!("Invalid value of {}: {}", arg, value));
+            match arg.as_str() {
+                "--engine" => options.engines.push(EngineConfig::parse(value)?),
+                "--pairs" => options.pairs = value.parse().map_err(|_| invalid())?,
+                "--threads" => options.threads = value.parse().map_err(|_| invalid())?,
+                "--seed" => options.seed = value.parse().map_err(|_| invalid())?,
+                "--openings" => set_openings(Openings::load(Path::new(value))?)?,
+                "--output" => options.output = value.clone(),
+                "--elo0" => options.elo0 = value.parse().map_err(|_| invalid())?,
+                "--elo1" => options.elo1 = value.parse().map_err(|_| invalid())?,
+                "--alpha" => options.alpha = value.parse().map_err(|_| invalid())?,
+                "--beta" => options.beta = value.parse().map_err(|_| invalid())?,
+                _ => return Err(ParseError(format!("Unknown option: {}", arg))),
+            }
+        }
+
+        if options.pairs == 0 {
+            return Err(ParseError("--pairs must be at least 1".to_string()));
+        }
+        if options.engines.len() != 2 {
+            return Err(ParseError("Exactly two engines must be given".to_string()));
+        }
+        Ok(options)
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let options = Options::parse(&args).map_err(|e| format!("{}\n{}", e, USAGE))?;
+    let [first, second] = [&options.engines[0], &options.engines[1]];
+
+    println!("{} vs {}: {} games on {} threads from seed {}",
+             first.name, second.name, 2 * options.pairs, options.threads, options.seed);
+
+    let mut output = BufWriter::new(File::create(&options.output)?);
+    let mut record = Record::default();
+    let mut write_error = None;
+    run_tournament(first, second, options.pairs, options.threads, options.seed, |game| {
+        record.add(game.outcome(&first.name));
+        println!("Game {}: {} {} - {} {}  ({}-{}-{})", record.games(), game.dark, game.dark_disks, game.light_disks,
+                 game.light, record.wins, record.draws, record.losses);
+
+        if write_error.is_none() {
+            write_error = serde_json::to_string(game).map_err(|e| e.to_string())
+                .and_then(|line| writeln!(output, "{}", line).map_err(|e| e.to_string()))
+                .err();
+        }
+
+        // The test is sequential, so it is checked after every game and stops the tournament once it decides
+        record.sprt(options.elo0, options.elo1, options.alpha, options.beta).verdict() == Verdict::Continue
+    })?;
+    output.flush()?;
+    if let Some(e) = write_error {
+        return Err(format!("Failed to write the games to {}: {}", options.output, e).into());
+    }
+
+    let (low, high) = record.elo_interval(Z_95);
+    let sprt = record.sprt(options.elo0, options.elo1, options.alpha, options.beta);
+    println!();
+    println!("{} vs {}: {} wins, {} draws, {} losses", first.name, second.name, record.wins, record.draws,
+             record.losses);
+    println!("Elo difference: {:+.1} (95% confidence interval {:+.1} to {:+.1})", record.elo(), low, high);
+    println!("SPRT of elo0 {} against elo1 {}: LLR {:.2} in [{:.2}, {:.2}], {} after {} of {} games", options.elo0,
+             options.elo1, sprt.llr, sprt.lower_bound, sprt.upper_bound, match sprt.verdict() {
+                 Verdict::AcceptH1 => "H1 accepted",
+                 Verdict::AcceptH0 => "H0 accepted",
+                 Verdict::Continue => "inconclusive",
+             }, record.games(), 2 * options.pairs);
+    println!("Games written to {}", options.output);
+    Ok(())
+}
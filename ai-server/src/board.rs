@@ -1,7 +1,15 @@
+use std::cmp::min;
 use std::fmt;
 use std::fmt::{Display, Formatter};
 
 use itertools::Itertools;
+use rand::rngs::StdRng;
+use rand::seq::IteratorRandom;
+use rand::SeedableRng;
+use schemars::gen::SchemaGenerator;
+use schemars::JsonSchema;
+use schemars::schema::Schema;
+use serde::{Deserialize, Serialize};
 
 use Direction::{East, North, NorthEast, NorthWest, South, SouthEast, SouthWest, West};
 
@@ -9,24 +17,65 @@ use crate::board::Disk::{Dark, Light};
 use crate::errors::Error;
 use crate::errors::Error::{InvalidArgument, ParseError};
 
-pub const BOARD_SIZE: usize = 8;
+/// Number of rows and columns of the board of a standard game
+pub const DEFAULT_BOARD_SIZE: usize = 8;
+pub const MIN_BOARD_SIZE: usize = 4;
+pub const MAX_BOARD_SIZE: usize = 16;
 
 pub const DARK_CHAR: char = 'D';
 pub const LIGHT_CHAR: char = 'L';
 pub const EMPTY_CHAR: char = 'E';
+pub const BLOCKED_CHAR: char = 'X';
 
-const POSITION_WEIGHTS: [[i32; BOARD_SIZE]; BOARD_SIZE] = [
-    [100, -10,  30,  20,  20,  30, -10, 100],
-    [-10, -10,   1,   2,   2,   1, -10, -10],
-    [ 30,   1,  10,   6,   6,  10,   1,  30],
-    [ 20,   2,   6,   0,   0,   6,   2,  20],
-    [ 20,   2,   6,   0,   0,   6,   2,  20],
-    [ 30,   1,  10,   6,   6,  10,   1,  30],
-    [-10, -10,   1,   2,   2,   1, -10, -10],
-    [100, -10,  30,  20,  20,  30, -10, 100]
+/// Checks that the given size of a board is even and within the bounds
+pub fn check_size(size: usize) -> Result<(), Error> {
+    if !size.is_multiple_of(2) || !(MIN_BOARD_SIZE..=MAX_BOARD_SIZE).contains(&size) {
+        return Err(InvalidArgument(format!(
+            "Board size must be even and between {} and {}: {}", MIN_BOARD_SIZE, MAX_BOARD_SIZE, size)));
+    }
+    Ok(())
+}
+
+/// Splits the given data into the cells of a square board of a valid size, whose size is the number of rows
+pub fn parse_grid(data: &str) -> Result<Vec<Vec<char>>, Error> {
+    let rows = data.lines().map(|line| line.chars().collect_vec()).collect_vec();
+    let size = rows.len();
+    check_size(size).map_err(|e| ParseError(e.to_string()))?;
+    if rows.iter().any(|row| row.len() != size) {
+        return Err(ParseError(format!("Board must have {} rows of {} cells", size, size)));
+    }
+    Ok(rows)
+}
+
+/// Writes the cells of a board of the given size row by row, one line per row
+pub fn format_grid<F>(size: usize, cell: F) -> String
+    where F: Fn(&Position) -> char {
+    (0..size)
+        .map(|row| (0..size).map(|col| cell(&Position::new(row, col))).collect::<String>())
+        .join("\n")
+}
+
+/// Weights of the squares by their distances to the nearest row edge and the nearest column edge,
+/// where squares further inside weigh like those at a distance of 3
+const EDGE_WEIGHTS: [[i32; 4]; 4] = [
+    [100, -10,  30,  20],
+    [-10, -10,   1,   2],
+    [ 30,   1,  10,   6],
+    [ 20,   2,   6,   0],
 ];
 
-#[derive(Copy, Clone, PartialEq, Debug)]
+/// Arrangement of the four disks in the centre of the board at the start of a game
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum Layout {
+    /// Disks of the same colour on the diagonals, as in a standard game
+    #[default]
+    Diagonal,
+    /// Dark disks on the upper row and light disks on the lower row
+    Straight,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
 pub enum Direction {
     North,
     NorthEast,
@@ -44,13 +93,34 @@ impl Direction {
     pub fn all() -> impl Iterator<Item=Direction> {
        vec![North, NorthEast, East, SouthEast, South, SouthWest, West, NorthWest].into_iter()
     }
+
+    /// Returns the pairs of opposite directions of the four lines through a square
+    pub fn axes() -> [(Direction, Direction); 4] {
+        [(North, South), (East, West), (NorthEast, SouthWest), (NorthWest, SouthEast)]
+    }
+
+    /// Returns the changes of row and column of a step in this direction
+    pub fn offset(&self) -> (i32, i32) {
+        match *self {
+            North => (-1, 0),
+            NorthEast => (-1, 1),
+            East => (0, 1),
+            SouthEast => (1, 1),
+            South => (1, 0),
+            SouthWest => (1, -1),
+            West => (0, -1),
+            NorthWest => (-1, -1),
+        }
+    }
 }
 
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Hash, Serialize, Deserialize, JsonSchema)]
 pub enum Disk {
     #[default]
+    #[serde(rename = "D")]
     Dark,
+    #[serde(rename = "L")]
     Light,
 }
 
@@ -73,28 +143,28 @@ impl Disk {
             _ => Err(ParseError(format!("Invalid character to parse into a disk: {}", ch))),
         }
     }
+
+    /// Returns the disk of the other colour
+    pub fn opposite(&self) -> Self {
+        match *self {
+            Dark => Light,
+            Light => Dark,
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Clone, Hash, Eq)]
+#[derive(Debug, PartialEq, Clone, Hash, Eq, PartialOrd, Ord, Default, Serialize, Deserialize, JsonSchema)]
 pub struct Position {
     row: usize,
     col: usize,
 }
 
-impl Default for Position {
-    fn default() -> Self {
-        Self {row: 0, col: 0}
-    }
-}
-
 impl Position {
     
     /// Parses the given string into a position
     pub fn parse(s: String) -> Result<Self, Error> {
         if let [row, col] = s.split(",")
-            .map(|s| s.parse::<usize>())
-            .filter(|result| result.is_ok())
-            .map(|result| result.unwrap())
+            .flat_map(|s| s.parse::<usize>())
             .collect_vec()[..] {
             Ok(Self {
                 row,
@@ -105,6 +175,25 @@ impl Position {
         }
     }
     
+    /// Parses the given coordinates in algebraic notation, such as "f5", into a position
+    pub fn parse_algebraic(s: &str) -> Result<Self, Error> {
+        let mut chars = s.chars();
+        let col = chars.next()
+            .filter(char::is_ascii_alphabetic)
+            .map(|ch| (ch.to_ascii_lowercase() as u8 - b'a') as usize);
+        let row = chars.as_str().parse::<usize>().ok().filter(|row| *row > 0);
+
+        match (row, col) {
+            (Some(row), Some(col)) => Ok(Self::new(row - 1, col)),
+            _ => Err(ParseError(format!("Invalid coordinates to parse into a position: {}", s))),
+        }
+    }
+
+    /// Returns the coordinates of this position in algebraic notation, such as "f5"
+    pub fn to_algebraic(&self) -> String {
+        format!("{}{}", (b'a' + self.col as u8) as char, self.row + 1)
+    }
+    
     /// Creates a new Position
     pub fn new(row: usize, col: usize) -> Self {
         Self {
@@ -112,10 +201,18 @@ impl Position {
             col,
         }
     }
+
+    pub fn row(&self) -> usize {
+        self.row
+    }
+
+    pub fn col(&self) -> usize {
+        self.col
+    }
     
-    /// Checks if this position is in bound
-    fn is_inbound(&self) -> bool {
-        self.row < BOARD_SIZE && self.col < BOARD_SIZE
+    /// Checks if this position is in bound of a board of the given size
+    pub fn is_inbound(&self, size: usize) -> bool {
+        self.row < size && self.col < size
     }
     
     /// Returns the direction towards the target
@@ -153,16 +250,55 @@ impl Position {
         }
     }
     
-    /// Returns the weight of this position
-    pub fn weight(&self) -> i32 {
-        POSITION_WEIGHTS[self.row][self.col]
+    /// Returns the weight of this position on a board of the given size
+    pub fn weight(&self, size: usize) -> i32 {
+        let distance = |i: usize| min(min(i, size - 1 - i), EDGE_WEIGHTS.len() - 1);
+        EDGE_WEIGHTS[distance(self.row)][distance(self.col)]
+    }
+
+    /// Checks if this position is a corner of a board of the given size
+    pub fn is_corner(&self, size: usize) -> bool {
+        (self.row == 0 || self.row == size - 1) && (self.col == 0 || self.col == size - 1)
+    }
+
+    /// Returns the corner next to this position on a board of the given size, if there is one
+    pub fn adjacent_corner(&self, size: usize) -> Option<Position> {
+        let nearest = |i: usize| if i <= 1 { Some(0) } else if i >= size - 2 { Some(size - 1) } else { None };
+
+        let corner = Position::new(nearest(self.row)?, nearest(self.col)?);
+        if corner == *self { None } else { Some(corner) }
+    }
+
+    /// Checks if this position is diagonally next to a corner of a board of the given size
+    pub fn is_x_square(&self, size: usize) -> bool {
+        self.adjacent_corner(size)
+            .is_some_and(|corner| corner.row != self.row && corner.col != self.col)
+    }
+
+    /// Checks if this position is on an edge next to a corner of a board of the given size
+    pub fn is_c_square(&self, size: usize) -> bool {
+        self.adjacent_corner(size)
+            .is_some_and(|corner| corner.row == self.row || corner.col == self.col)
+    }
+
+    /// Returns the neighbour of this position at the given direction on a board of the given size
+    pub fn neighbour(&self, dir: Direction, size: usize) -> Option<Position> {
+        let (row, col) = dir.offset();
+        let neighbour = Position::new((self.row as i32 + row) as usize, (self.col as i32 + col) as usize);
+
+        if neighbour.is_inbound(size) { Some(neighbour) } else { None }
+    }
+
+    /// Returns the positions from this one, excluded, to the edge of a board of the given size at the given direction
+    pub fn ray(&self, dir: Direction, size: usize) -> impl Iterator<Item=Position> {
+        std::iter::successors(self.neighbour(dir, size), move |pos| pos.neighbour(dir, size))
     }
 
-    /// Returns all possible positions of the board
-    pub fn all() -> impl Iterator<Item=Position> {
+    /// Returns all possible positions of a board of the given size
+    pub fn all(size: usize) -> impl Iterator<Item=Position> {
         let mut positions = vec![];
-        for i in 0..BOARD_SIZE {
-            for j in 0..BOARD_SIZE {
+        for i in 0..size {
+            for j in 0..size {
                 positions.push(Position::new(i, j));
             }
         }
@@ -178,77 +314,188 @@ impl Display for Position {
     }
 }
 
-#[derive(Default, Clone, PartialEq, Eq, Hash)]
+/// Square board with an even number of rows and columns
+///
+/// The grid has room for the largest board, and only its first `size` rows and columns are used.
+/// Blocked squares are holes in the board, which never hold a disk and end every line through them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "Vec<String>", into = "Vec<String>")]
 pub struct Board {
-    grid: [[Option<Disk>; BOARD_SIZE]; BOARD_SIZE],
+    size: usize,
+    grid: [[Option<Disk>; MAX_BOARD_SIZE]; MAX_BOARD_SIZE],
+    blocked: [[bool; MAX_BOARD_SIZE]; MAX_BOARD_SIZE],
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Board::new()
+    }
 }
 
 impl Display for Board {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        let mut buf = String::with_capacity(BOARD_SIZE * BOARD_SIZE + BOARD_SIZE);
-        
-        for row in self.grid.iter() {
-            for cell in row.iter() {
-                buf.push(match cell {
-                    None => EMPTY_CHAR,
-                    Some(disk) => disk.to_string().chars().nth(0).unwrap(),
-                })
-            }
-            
-            buf.push('\n');
-        }
-        
-        write!(f, "{}", buf.trim())
+        let rows = format_grid(self.size, |pos| match self.disk(pos) {
+            None if self.is_blocked(pos) => BLOCKED_CHAR,
+            None => EMPTY_CHAR,
+            Some(Dark) => DARK_CHAR,
+            Some(Light) => LIGHT_CHAR,
+        });
+
+        write!(f, "{}", rows)
+    }
+}
+
+impl TryFrom<Vec<String>> for Board {
+    type Error = Error;
+
+    fn try_from(rows: Vec<String>) -> Result<Self, Self::Error> {
+        Board::parse(rows.join("\n"))
+    }
+}
+
+impl From<Board> for Vec<String> {
+    fn from(board: Board) -> Self {
+        board.to_string().lines().map(|line| line.to_string()).collect()
+    }
+}
+
+impl JsonSchema for Board {
+    fn schema_name() -> String {
+        "Board".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let mut schema = gen.subschema_for::<Vec<String>>().into_object();
+        schema.metadata().description = Some(format!(
+            "Rows of a square board of an even size, where each cell is '{}' for dark, '{}' for light, '{}' for empty \
+            or '{}' for blocked", DARK_CHAR, LIGHT_CHAR, EMPTY_CHAR, BLOCKED_CHAR));
+        schema.array().min_items = Some(MIN_BOARD_SIZE as u32);
+        schema.array().max_items = Some(MAX_BOARD_SIZE as u32);
+        schema.into()
     }
 }
 
 impl Board {
     
-    /// Creates a new board
+    /// Creates a new board of the standard size
     pub fn new() -> Self {
-        assert_eq!(BOARD_SIZE % 2, 0, "Board size must be even");
-        
+        Self::initial(DEFAULT_BOARD_SIZE, Layout::Diagonal)
+    }
+
+    /// Creates a new board of the given size
+    pub fn with_size(size: usize) -> Result<Self, Error> {
+        Self::with_layout(size, Layout::Diagonal)
+    }
+
+    /// Creates a new board of the given size with the centre disks in the given layout
+    pub fn with_layout(size: usize, layout: Layout) -> Result<Self, Error> {
+        check_size(size)?;
+        Ok(Self::initial(size, layout))
+    }
+
+    /// Creates a new board of the given size with the given number of blocked squares, chosen at random
+    /// from the seed among the empty squares
+    pub fn with_holes(size: usize, holes: usize, seed: u64) -> Result<Self, Error> {
+        let mut board = Self::with_size(size)?;
+        board.block_random(holes, seed)?;
+        Ok(board)
+    }
+
+    /// Blocks the given number of empty squares, chosen at random from the seed, leaving at least one to play
+    pub fn block_random(&mut self, holes: usize, seed: u64) -> Result<(), Error> {
+        let empties = self.empty_positions().count();
+        if holes >= empties {
+            return Err(InvalidArgument(format!("The board has room for at most {} holes",
+                                               empties.saturating_sub(1))));
+        }
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        for pos in self.empty_positions().choose_multiple(&mut rng, holes) {
+            self.block(&pos)?;
+        }
+        Ok(())
+    }
+
+    /// Creates the board at the start of a game of the given valid size
+    fn initial(size: usize, layout: Layout) -> Self {
         let mut board = Board {
-            grid: [[None; BOARD_SIZE]; BOARD_SIZE]
+            size,
+            grid: [[None; MAX_BOARD_SIZE]; MAX_BOARD_SIZE],
+            blocked: [[false; MAX_BOARD_SIZE]; MAX_BOARD_SIZE],
         };
         
-        let mid_pos = Position::new(BOARD_SIZE / 2 - 1, BOARD_SIZE / 2 - 1);
+        let mid_pos = Position::new(size / 2 - 1, size / 2 - 1);
+        
+        let upper_right = match layout {
+            Layout::Diagonal => Light,
+            Layout::Straight => Dark,
+        };
         
         board.grid[mid_pos.row][mid_pos.col] = Some(Dark);
         board.grid[mid_pos.row + 1][mid_pos.col] = Some(Light);
-        board.grid[mid_pos.row][mid_pos.col + 1] = Some(Light);
-        board.grid[mid_pos.row + 1][mid_pos.col + 1] = Some(Dark);
+        board.grid[mid_pos.row][mid_pos.col + 1] = Some(upper_right);
+        board.grid[mid_pos.row + 1][mid_pos.col + 1] = Some(upper_right.opposite());
 
         board
     }
     
-    /// Parses the given data to a board
+    /// Parses the given data to a board, whose size is the number of rows
     pub fn parse(data: String) -> Result<Self, Error> {
-        let mut board = Board::new();
-        for (i, line) in data.lines().enumerate() {
-            for (j, ch) in line.chars().enumerate() {
-                let disk = if ch == EMPTY_CHAR {
+        let rows = parse_grid(&data)?;
+        let mut board = Board::initial(rows.len(), Layout::Diagonal);
+
+        for (i, row) in rows.into_iter().enumerate() {
+            for (j, ch) in row.into_iter().enumerate() {
+                let disk = if ch == EMPTY_CHAR || ch == BLOCKED_CHAR {
                     None
                 } else {
                     Some(Disk::parse(ch)?)
                 };
 
                 board.grid[i][j] = disk;
+                board.blocked[i][j] = ch == BLOCKED_CHAR;
             }
         }
         Ok(board)
     }
     
+    /// Returns the number of rows and columns of this board
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
     /// Returns the disk at the given position
     pub fn disk(&self, pos: &Position) -> Option<Disk> {
         self.grid[pos.row][pos.col]
     }
     
-    /// Places the disk at the given position
+    /// Checks if the given position is a hole in the board
+    pub fn is_blocked(&self, pos: &Position) -> bool {
+        self.blocked[pos.row][pos.col]
+    }
+
+    /// Checks if a disk can be placed at the given position
+    pub fn is_empty(&self, pos: &Position) -> bool {
+        self.disk(pos).is_none() && !self.is_blocked(pos)
+    }
+
+    /// Makes a hole in the board at the given position
     /// Pre-conditions:
     /// * Given position isn't occupied by a disk
-    pub fn place(&mut self, disk: Disk, pos: &Position) -> Result<(), Error> {
+    pub fn block(&mut self, pos: &Position) -> Result<(), Error> {
         if self.disk(pos).is_some() {
+            return Err(InvalidArgument(format!("Given position is not empty to block: {}", pos)));
+        }
+
+        self.blocked[pos.row][pos.col] = true;
+        Ok(())
+    }
+    
+    /// Places the disk at the given position
+    /// Pre-conditions:
+    /// * Given position is empty
+    pub fn place(&mut self, disk: Disk, pos: &Position) -> Result<(), Error> {
+        if !self.is_empty(pos) {
             return Err(InvalidArgument(
                 format!("Given position is not empty to place a disk: {}", pos)));
         }
@@ -259,11 +506,35 @@ impl Board {
     
     /// Returns all positions of the given disk
     pub fn positions(&self, disk: Disk) -> impl Iterator<Item=Position> {
+        self.cells().filter(move |(_, d)| *d == Some(disk)).map(|(pos, _)| pos)
+    }
+
+    /// Returns all positions where a disk can be placed
+    pub fn empty_positions(&self) -> impl Iterator<Item=Position> {
+        let blocked = self.blocked;
+        self.cells()
+            .filter(move |(pos, d)| d.is_none() && !blocked[pos.row][pos.col])
+            .map(|(pos, _)| pos)
+    }
+
+    /// Returns all holes in the board
+    pub fn blocked_positions(&self) -> impl Iterator<Item=Position> {
+        let blocked = self.blocked;
+        self.cells()
+            .filter(move |(pos, _)| blocked[pos.row][pos.col])
+            .map(|(pos, _)| pos)
+    }
+
+    /// Returns every position of this board with its disk, row by row
+    fn cells(&self) -> impl Iterator<Item=(Position, Option<Disk>)> {
+        let size = self.size;
         self.grid.into_iter()
-            .flatten()
+            .take(size)
             .enumerate()
-            .filter(move |(_, d)| d.is_some() && d.unwrap() == disk)
-            .map(|(i, _)| Position::new(i / BOARD_SIZE, i % BOARD_SIZE))
+            .flat_map(move |(i, row)| row.into_iter()
+                .take(size)
+                .enumerate()
+                .map(move |(j, d)| (Position::new(i, j), d)))
     }
     
     /// Flips the disk at the given position
@@ -271,7 +542,7 @@ impl Board {
     /// * pos.is_inbound()
     /// * The given position must be occupied by a disk
     pub fn flip(&mut self, pos: &Position) -> Result<(), Error> {
-        assert!(pos.is_inbound());
+        assert!(pos.is_inbound(self.size));
 
         match self.disk(pos) {
             None => Err(InvalidArgument(format!("Board is empty at {}", pos))),
@@ -285,9 +556,8 @@ impl Board {
     /// Returns the neighbours of the given position
     /// Pre-conditions:
     /// * pos.is_inbound()
-    #[cfg(test)]
     pub fn neighbours(&self, pos: &Position) -> impl Iterator<Item=Position> {
-        assert!(pos.is_inbound());
+        assert!(pos.is_inbound(self.size));
         
         let mut neighbours = Vec::with_capacity(9);
         
@@ -300,53 +570,139 @@ impl Board {
         }
         
         let pos = pos.clone();
+        let size = self.size;
         neighbours.into_iter()
             .filter(move |neighbour| *neighbour != pos)
-            .filter(|p| p.is_inbound())
+            .filter(move |p| p.is_inbound(size))
     }
     
     /// Returns the neighbour from the given position at the given direction
     /// Pre-conditions:
     /// * pos.is_inbound()
     pub fn neighbour(&self, pos: &Position, dir: Direction) -> Option<Position> {
-        assert!(pos.is_inbound());
+        assert!(pos.is_inbound(self.size));
         
-        let offset = match dir {
-            North => (-1, 0),
-            NorthEast => (-1, 1),
-            East => (0, 1),
-            SouthEast => (1, 1),
-            South => (1, 0),
-            SouthWest => (1, -1),
-            West => (0, -1),
-            NorthWest => (-1, -1),
+        pos.neighbour(dir, self.size)
+    }
+
+    /// Returns the disks of the given colour that can never be flipped
+    ///
+    /// A disk is stable when each of its four lines is either full, or continues beyond the disk with the
+    /// edge of the board, a hole or a stable disk of the same colour in one direction.
+    pub fn stable_positions(&self, disk: Disk) -> Vec<Position> {
+        let mut stable = [[false; MAX_BOARD_SIZE]; MAX_BOARD_SIZE];
+        let is_stable = |stable: &[[bool; MAX_BOARD_SIZE]; MAX_BOARD_SIZE], pos: Option<Position>| {
+            pos.is_none_or(|pos| stable[pos.row][pos.col] || self.is_blocked(&pos))
         };
-        
-        let neighbour = Position::new((pos.row as i32 + offset.0) as usize, 
-                      (pos.col as i32 + offset.1) as usize);
-        
-        if neighbour.is_inbound() {Some(neighbour)} else {None}
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for pos in self.positions(disk) {
+                if stable[pos.row][pos.col] {
+                    continue;
+                }
+
+                let anchored = Direction::axes().into_iter().all(|(forward, backward)| {
+                    self.is_line_full(&pos, forward, backward) ||
+                        is_stable(&stable, self.neighbour(&pos, forward)) ||
+                        is_stable(&stable, self.neighbour(&pos, backward))
+                });
+                if anchored {
+                    stable[pos.row][pos.col] = true;
+                    changed = true;
+                }
+            }
+        }
+
+        Position::all(self.size)
+            .filter(|pos| stable[pos.row][pos.col])
+            .collect()
+    }
+
+    /// Checks if every square of the line through the given position along the given directions has a disk,
+    /// up to the edges of the board or the nearest holes
+    fn is_line_full(&self, pos: &Position, forward: Direction, backward: Direction) -> bool {
+        [forward, backward].into_iter().all(|dir| {
+            let mut walker = self.neighbour(pos, dir);
+            while let Some(current) = walker {
+                if self.is_blocked(&current) {
+                    return true;
+                }
+                if self.disk(&current).is_none() {
+                    return false;
+                }
+                walker = self.neighbour(&current, dir);
+            }
+            true
+        })
+    }
+
+    /// Returns the disks of the given colour next to an empty square
+    pub fn frontier_positions(&self, disk: Disk) -> impl Iterator<Item=Position> + '_ {
+        self.positions(disk)
+            .filter(|pos| self.neighbours(pos).any(|neighbour| self.is_empty(&neighbour)))
+    }
+
+    /// Returns the number of empty squares next to a disk of the opposite colour, where the player of the given
+    /// colour may be able to move later
+    pub fn potential_mobility(&self, disk: Disk) -> usize {
+        self.empty_positions()
+            .filter(|pos| self.neighbours(pos).any(|neighbour| self.disk(&neighbour) == Some(disk.opposite())))
+            .count()
+    }
+
+    /// Returns the groups of empty squares connected to each other, including diagonally
+    pub fn empty_regions(&self) -> Vec<Vec<Position>> {
+        let mut visited = [[false; MAX_BOARD_SIZE]; MAX_BOARD_SIZE];
+        let mut regions = Vec::new();
+
+        for start in self.empty_positions() {
+            if visited[start.row][start.col] {
+                continue;
+            }
+            visited[start.row][start.col] = true;
+
+            let mut region = Vec::new();
+            let mut stack = vec![start];
+            while let Some(pos) = stack.pop() {
+                for neighbour in self.neighbours(&pos) {
+                    if self.is_empty(&neighbour) && !visited[neighbour.row][neighbour.col] {
+                        visited[neighbour.row][neighbour.col] = true;
+                        stack.push(neighbour);
+                    }
+                }
+                region.push(pos);
+            }
+
+            region.sort();
+            regions.push(region);
+        }
+
+        regions
     }
 
     #[cfg(test)]
     /// Clears this board
     pub fn clear(&mut self) {
-        self.grid = [[None; BOARD_SIZE]; BOARD_SIZE];
+        self.grid = [[None; MAX_BOARD_SIZE]; MAX_BOARD_SIZE];
+        self.blocked = [[false; MAX_BOARD_SIZE]; MAX_BOARD_SIZE];
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::board::{Board, BOARD_SIZE, Direction, Disk, Position};
+    use itertools::Itertools;
+
+    use crate::board::{Board, DEFAULT_BOARD_SIZE as BOARD_SIZE, Direction, Disk, Layout, Position};
     use crate::board::Direction::{East, North, NorthEast, NorthWest, South, SouthEast, SouthWest, West};
     use crate::board::Disk::{Dark, Light};
 
     #[test]
     fn new() {
         let board = Board::new();
-
-        // This test is only correct when BOARD_SIZE == 8
-        assert_eq!(BOARD_SIZE, 8);
+        assert_eq!(board.size(), 8);
 
         assert_eq!(board.grid[3][3], Some(Dark));
         assert_eq!(board.grid[4][4], Some(Dark));
@@ -363,7 +719,24 @@ mod tests {
         EEEEEEEE\n\
         EEEEEEEE\n\
         EEEEEEEE"
-        )
+        );
+
+        let board = Board::with_size(4).unwrap();
+        assert_eq!(board.to_string(), "EEEE\nEDLE\nELDE\nEEEE");
+        assert!(Board::with_size(5).is_err());
+        assert!(Board::with_size(2).is_err());
+        assert!(Board::with_size(18).is_err());
+    }
+
+    #[test]
+    fn parse() {
+        let board = Board::parse("EEEEEE\nEEEEEE\nEEDLEE\nEELDEE\nEEEEEE\nEEEEEE".to_string()).unwrap();
+        assert_eq!(board.size(), 6);
+        assert_eq!(board.disk(&Position::new(2, 2)), Some(Dark));
+        assert_eq!(board.empty_positions().count(), 32);
+
+        assert!(Board::parse("EEEEEE\nEEEEEE\nEEDLEE\nEELDEE\nEEEEEE\nEEEEE".to_string()).is_err());
+        assert!(Board::parse("EEEEE\nEEEEE\nEEDLE\nEELDE\nEEEEE".to_string()).is_err());
     }
     
     #[test]
@@ -397,7 +770,7 @@ mod tests {
         let board = Board::new();
 
         let get_result = |pos: &Position| -> Vec<String> {
-            board.neighbours(&pos)
+            board.neighbours(pos)
                 .map(|pos| pos.to_string())
                 .collect()
         };
@@ -464,6 +837,10 @@ mod tests {
         assert_eq!(get_result(&pos, SouthEast), None);
         assert_eq!(get_result(&pos, South), None);
         assert_eq!(get_result(&pos, SouthWest), None);
+
+        let ray = Position::new(5, 2).ray(NorthEast, BOARD_SIZE).map(|pos| pos.to_string()).collect::<Vec<_>>();
+        assert_eq!(ray, vec!["4,3", "3,4", "2,5", "1,6", "0,7"]);
+        assert_eq!(Position::new(0, 0).ray(North, BOARD_SIZE).count(), 0);
     }
     
     #[test]
@@ -512,4 +889,137 @@ mod tests {
         let target = Position::new(center.row - 2, center.col);
         assert_eq!(center.direction(&target), North);
     }
+
+    #[test]
+    fn layouts() {
+        let board = Board::with_layout(4, Layout::Straight).unwrap();
+        assert_eq!(board.to_string(), "EEEE\nEDDE\nELLE\nEEEE");
+        assert_eq!(Board::with_layout(8, Layout::Diagonal).unwrap(), Board::new());
+    }
+
+    #[test]
+    fn algebraic() {
+        assert_eq!(Position::parse_algebraic("f5").unwrap(), Position::new(4, 5));
+        assert_eq!(Position::parse_algebraic("A1").unwrap(), Position::new(0, 0));
+        assert_eq!(Position::new(9, 11).to_algebraic(), "l10");
+        assert!(Position::parse_algebraic("5f").is_err());
+        assert!(Position::parse_algebraic("a0").is_err());
+    }
+
+    #[test]
+    fn holes() {
+        let board = Board::parse("XEEX\nEDLE\nELDE\nEEEX".to_string()).unwrap();
+        assert!(board.is_blocked(&Position::new(0, 0)));
+        assert!(!board.is_empty(&Position::new(0, 0)));
+        assert_eq!(board.disk(&Position::new(0, 0)), None);
+        assert_eq!(board.empty_positions().count(), 9);
+        assert_eq!(board.blocked_positions().count(), 3);
+        assert_eq!(board.to_string(), "XEEX\nEDLE\nELDE\nEEEX");
+
+        let mut board = board.clone();
+        assert!(board.place(Dark, &Position::new(0, 3)).is_err());
+        assert!(board.block(&Position::new(1, 1)).is_err());
+
+        let board = Board::with_holes(8, 6, 42).unwrap();
+        assert_eq!(board.blocked_positions().count(), 6);
+        assert_eq!(board, Board::with_holes(8, 6, 42).unwrap());
+        assert_ne!(board, Board::with_holes(8, 6, 43).unwrap());
+        assert!(Board::with_holes(4, 12, 0).is_err());
+        assert_eq!(Board::with_holes(4, 11, 0).unwrap().empty_positions().count(), 1);
+    }
+
+    #[test]
+    fn squares() {
+        assert!(Position::new(0, 7).is_corner(8));
+        assert!(!Position::new(0, 6).is_corner(8));
+        assert!(Position::new(9, 0).is_corner(10));
+
+        assert_eq!(Position::new(6, 1).adjacent_corner(8), Some(Position::new(7, 0)));
+        assert_eq!(Position::new(0, 0).adjacent_corner(8), None);
+        assert_eq!(Position::new(2, 1).adjacent_corner(8), None);
+        assert_eq!(Position::new(8, 1).adjacent_corner(10), Some(Position::new(9, 0)));
+
+        assert!(Position::new(1, 1).is_x_square(8));
+        assert!(!Position::new(1, 1).is_c_square(8));
+        assert!(Position::new(0, 6).is_c_square(8));
+        assert!(Position::new(6, 7).is_c_square(8));
+        assert!(!Position::new(6, 7).is_x_square(8));
+    }
+
+    #[test]
+    fn weights() {
+        let weights = |size: usize| Position::all(size).map(|pos| pos.weight(size)).collect_vec();
+
+        assert_eq!(weights(8)[..16], [
+            100, -10, 30, 20, 20, 30, -10, 100,
+            -10, -10, 1, 2, 2, 1, -10, -10,
+        ]);
+        assert_eq!(weights(4), [
+            100, -10, -10, 100,
+            -10, -10, -10, -10,
+            -10, -10, -10, -10,
+            100, -10, -10, 100,
+        ]);
+        assert_eq!(weights(10)[..10], [100, -10, 30, 20, 20, 20, 20, 30, -10, 100]);
+        assert_eq!(Position::new(4, 5).weight(10), 0);
+    }
+
+    #[test]
+    fn stable_positions() {
+        let mut board = Board::new();
+        board.clear();
+        for j in 0..3 {
+            board.place(Dark, &Position::new(0, j)).unwrap();
+        }
+        board.place(Dark, &Position::new(1, 0)).unwrap();
+        board.place(Dark, &Position::new(1, 1)).unwrap();
+        board.place(Light, &Position::new(1, 2)).unwrap();
+        board.place(Dark, &Position::new(4, 4)).unwrap();
+
+        assert_eq!(board.stable_positions(Dark), vec![
+            Position::new(0, 0), Position::new(0, 1), Position::new(0, 2), Position::new(1, 0), Position::new(1, 1),
+        ]);
+        assert!(board.stable_positions(Light).is_empty());
+
+        // Every disk of a full board is stable
+        for pos in board.empty_positions().collect::<Vec<_>>() {
+            board.place(Light, &pos).unwrap();
+        }
+        assert_eq!(board.stable_positions(Dark).len(), 6);
+        assert_eq!(board.stable_positions(Light).len(), BOARD_SIZE * BOARD_SIZE - 6);
+
+        // Holes anchor disks like edges
+        let board = Board::parse("EEEE\nXDXE\nEXEE\nEEEE".to_string()).unwrap();
+        assert!(board.stable_positions(Dark).is_empty());
+        let board = Board::parse("XXXE\nXDXE\nXXXE\nEEEE".to_string()).unwrap();
+        assert_eq!(board.stable_positions(Dark), vec![Position::new(1, 1)]);
+    }
+
+    #[test]
+    fn frontier() {
+        let board = Board::new();
+        assert_eq!(board.frontier_positions(Dark).count(), 2);
+        assert_eq!(board.potential_mobility(Dark), 10);
+
+        let mut board = Board::new();
+        board.clear();
+        board.place(Dark, &Position::new(0, 0)).unwrap();
+        assert_eq!(board.potential_mobility(Dark), 0);
+        assert_eq!(board.potential_mobility(Light), 3);
+    }
+
+    #[test]
+    fn empty_regions() {
+        let mut board = Board::new();
+        board.clear();
+        for i in 0..BOARD_SIZE {
+            board.place(Dark, &Position::new(i, 2)).unwrap();
+        }
+        board.place(Light, &Position::new(0, 0)).unwrap();
+
+        let regions = board.empty_regions();
+        assert_eq!(regions.len(), 2);
+        assert_eq!(regions.iter().map(|region| region.len()).sorted().collect_vec(),
+                   vec![BOARD_SIZE * 2 - 1, BOARD_SIZE * (BOARD_SIZE - 3)]);
+    }
 }
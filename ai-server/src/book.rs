@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::board::{Board, Position};
+use crate::errors::Error;
+use crate::errors::Error::InvalidConfig;
+
+pub const ROW_SEPARATOR: char = '/';
+pub const COMMENT_CHAR: char = '#';
+
+/// Opening book that maps known boards to the move the bot should play
+#[derive(Default)]
+pub struct Book {
+    moves: HashMap<Board, Position>,
+}
+
+impl Book {
+
+    /// Parses the given data into a book
+    ///
+    /// Each non-empty line holds a board, with its rows separated by '/', followed by the position to play
+    pub fn parse(data: &str) -> Result<Self, Error> {
+        let mut moves = HashMap::new();
+
+        for (i, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(COMMENT_CHAR) {
+                continue;
+            }
+
+            let (board, position) = line.split_once(char::is_whitespace)
+                .ok_or_else(|| InvalidConfig(format!("Missing position at line {} of the book", i + 1)))?;
+
+            let board = Board::parse(board.replace(ROW_SEPARATOR, "\n"))?;
+            let position = Position::parse(position.trim().to_string())?;
+
+            moves.insert(board, position);
+        }
+
+        Ok(Self { moves })
+    }
+
+    /// Loads the book from the given file
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let data = fs::read_to_string(path)
+            .map_err(|e| InvalidConfig(format!("Failed to read book file {}: {}", path.display(), e)))?;
+
+        Self::parse(&data)
+    }
+
+    /// Returns the move of the book for the given board
+    pub fn lookup(&self, board: &Board) -> Option<&Position> {
+        self.moves.get(board)
+    }
+
+    /// Returns the number of boards in this book
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    /// Checks if this book has no boards
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::{Board, Position};
+    use crate::book::Book;
+
+    #[test]
+    fn parse() {
+        let book = Book::parse("\
+        # Opening moves\n\
+        EEEEEEEE/EEEEEEEE/EEEEEEEE/EEEDLEEE/EEELDEEE/EEEEEEEE/EEEEEEEE/EEEEEEEE 2,3\n\
+        \n").unwrap();
+
+        assert_eq!(book.len(), 1);
+        assert_eq!(book.lookup(&Board::new()), Some(&Position::new(2, 3)));
+
+        assert!(Book::parse("EEEEEEEE/EEEEEEEE").is_err());
+    }
+}
@@ -1,15 +1,66 @@
 use std::cmp::{max, min};
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
 
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::book::Book;
 use crate::errors::Error;
 use crate::errors::Error::InvalidArgument;
-use crate::game::{Action, Game, max_best_evaluation, min_best_evaluation};
+use crate::game::{Action, Game, max_best_evaluation, min_best_evaluation, Variant, Weights};
 use crate::game::Player;
 
+pub const DEFAULT_CACHE_SIZE: usize = 1_000_000;
+
+/// Number of nodes expanded between the checks of the deadline
+pub const DEADLINE_CHECK_INTERVAL: u32 = 256;
+
+/// Mistakes of a weakened bot, which plays more like a human than the best move it finds
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Mistakes {
+    /// Spread of the choice among the moves in evaluation points, where a move worse by this much is about three
+    /// times less likely to be played and 0 always plays the best move
+    pub temperature: f32,
+    /// Chance of overlooking each move further ahead than the horizon
+    #[serde(default)]
+    pub oversight: f32,
+    /// Number of moves ahead of which no move is overlooked, where the replies to the move of the bot are the first
+    #[serde(default)]
+    pub horizon: u32,
+    /// Seed of the random choices, so that the same game is played the same way
+    #[serde(default)]
+    pub seed: u64,
+}
+
+impl Mistakes {
+
+    /// Checks that the temperature isn't negative and the oversight is a probability
+    pub fn validate(&self) -> Result<(), Error> {
+        if !(self.temperature >= 0.0 && self.temperature.is_finite()) {
+            return Err(InvalidArgument(format!("Invalid temperature: {}", self.temperature)));
+        }
+        if !(0.0..=1.0).contains(&self.oversight) {
+            return Err(InvalidArgument(format!("Invalid oversight: {}", self.oversight)));
+        }
+        Ok(())
+    }
+}
+
 pub struct Bot {
     depth_limit: u32,
     game: Game,
     minimax_cache: HashMap<Game, i32>,
+    cache_size: usize,
+    book: Option<Arc<Book>>,
+    weights: Option<Arc<Weights>>,
+    deadline: Option<Instant>,
+    timed_out: bool,
+    mistakes: Option<Mistakes>,
+    rng: StdRng,
     pub num_nodes_expanded: u32,
 }
 
@@ -21,9 +72,41 @@ impl Bot {
             depth_limit: intelligence,
             game: Game::new(),
             minimax_cache: HashMap::new(),
+            cache_size: DEFAULT_CACHE_SIZE,
+            book: None,
+            weights: None,
+            deadline: None,
+            timed_out: false,
+            mistakes: None,
+            rng: StdRng::seed_from_u64(0),
             num_nodes_expanded: 0,
         }
     }
+
+    /// Sets the maximum number of evaluations to keep in the cache
+    pub fn set_cache_size(&mut self, cache_size: usize) {
+        self.cache_size = cache_size;
+    }
+
+    /// Sets the opening book to play from before searching
+    pub fn set_book(&mut self, book: Arc<Book>) {
+        self.book = Some(book);
+    }
+
+    /// Sets the weights to evaluate games with instead of the ones in use
+    pub fn set_weights(&mut self, weights: Arc<Weights>) {
+        self.weights = Some(weights);
+    }
+
+    /// Sets the time by which decide_iteratively must return, cutting the search short
+    pub fn set_deadline(&mut self, deadline: Instant) {
+        self.deadline = Some(deadline);
+    }
+
+    /// Makes the bot play with the given mistakes, drawing them from their seed
+    pub fn set_mistakes(&mut self, mistakes: Mistakes) {
+        self.mistakes = Some(mistakes);
+    }
     
     /// Decides the next action from the given state
     /// 
@@ -33,9 +116,29 @@ impl Bot {
         assert_eq!(self.game.current_player(), Player::Bot);
         
         self.num_nodes_expanded = 1;
+
+        // The book holds openings of standard games only
+        let book = self.book.as_ref().filter(|_| game.variant() == Variant::Standard);
+        if let Some(position) = book.and_then(|book| book.lookup(game.board())) {
+            let action = Action::parse(Player::Bot, position.clone());
+            if game.actions(Player::Bot).any(|act| act == action) {
+                let result = game.result(&action);
+                return Ok((action, result));
+            }
+        }
         
-        let mut bot_best = min_best_evaluation();
-        let human_best = max_best_evaluation();
+        let size = game.board().size();
+        let mut bot_best = min_best_evaluation(size);
+        let human_best = max_best_evaluation(size);
+
+        // Every search draws the same mistakes, so a depth gives the same move however many depths ran before it
+        if let Some(mistakes) = self.mistakes {
+            self.rng = StdRng::seed_from_u64(mistakes.seed);
+        }
+        let actions = self.actions(game, Player::Bot, 0);
+        if let Some(temperature) = self.mistakes.map(|mistakes| mistakes.temperature).filter(|t| *t > 0.0) {
+            return self.choose(game, actions, temperature);
+        }
         
         let mut minimax_value = bot_best;
         let mut num_actions = 0;
@@ -44,11 +147,12 @@ impl Bot {
         let mut best_action = Action::default();
         let mut best_result= Game::default();
         
-        for act in game.actions(Player::Bot) {
+        for act in actions {
             num_actions += 1;
             let result = game.result(&act);
             let value = self.min_value(result.clone(), bot_best, human_best, 1);
-            if value >= minimax_value {
+            // A move cut off at the best value so far is at most as good, so only a better one replaces it
+            if value > minimax_value || !decided {
                 minimax_value = value;
                 best_action = act;
                 best_result = result;
@@ -58,54 +162,191 @@ impl Bot {
         }
 
         if num_actions == 0 {
-            return Err(InvalidArgument(format!("No actions are available from the given game.")));
+            return Err(InvalidArgument("No actions are available from the given game.".to_string()));
         }
         
         assert!(decided);
         
         Ok((best_action, best_result))
     }
+
+    /// Chooses among the given actions at random, where the better an action is for the bot the more likely it is
+    /// chosen, following a softmax of the values of the actions with the given temperature
+    fn choose(&mut self, game: &Game, actions: Vec<Action>, temperature: f32) -> Result<(Action, Game), Error> {
+        let size = game.board().size();
+        let values = actions.iter()
+            .map(|act| self.min_value(game.result(act), min_best_evaluation(size), max_best_evaluation(size), 1))
+            .collect::<Vec<_>>();
+
+        let Some(best) = values.iter().max().copied() else {
+            return Err(InvalidArgument("No actions are available from the given game.".to_string()));
+        };
+        // Shifting by the best value keeps the weights from overflowing
+        let weights = values.iter()
+            .map(|value| ((value - best) as f64 / temperature as f64).exp())
+            .collect::<Vec<_>>();
+
+        let mut target = self.rng.gen::<f64>() * weights.iter().sum::<f64>();
+        let index = weights.iter()
+            .position(|weight| {
+                target -= weight;
+                target < 0.0
+            })
+            .unwrap_or(weights.len() - 1);
+
+        let action = actions[index].clone();
+        let result = game.result(&action);
+        Ok((action, result))
+    }
+    
+    /// Decides the next action like decide, but searches one depth deeper at a time and reports the number of
+    /// moves looked ahead with the best action found so far after each depth.
+    /// When the deadline passes, the action of the deepest completed search is returned.
+    pub fn decide_iteratively<F>(&mut self, game: &Game, mut progress: F) -> Result<(Action, Game), Error>
+        where F: FnMut(u32, &Action) {
+        let depth_limit = self.depth_limit;
+        let mut decision = Err(InvalidArgument("No actions are available from the given game.".to_string()));
+        self.timed_out = false;
+
+        for depth in 0..=depth_limit {
+            self.depth_limit = depth;
+            let result = self.decide(game);
+
+            // The search of the first depth is kept even if it was cut short, to have an action at all
+            if self.timed_out && depth > 0 {
+                break;
+            }
+            decision = result;
+
+            match &decision {
+                Ok((action, _)) => progress(depth + 1, action),
+                Err(_) => break,
+            }
+            if self.timed_out {
+                break;
+            }
+        }
+
+        self.depth_limit = depth_limit;
+        decision
+    }
+
+    /// Searches the given game to the depth limit as if it was reached by one move, and returns its value for the bot
+    pub fn search(&mut self, game: &Game) -> i32 {
+        let mut game = game.clone();
+        if !game.is_over() && game.actions(game.current_player()).next().is_none() {
+            game = game.pass();
+        }
+
+        let size = game.board().size();
+        match game.current_player() {
+            Player::Bot => self.max_value(game, min_best_evaluation(size), max_best_evaluation(size), 1),
+            Player::Human => self.min_value(game, min_best_evaluation(size), max_best_evaluation(size), 1),
+        }
+    }
+
+    /// Returns the best actions of both players from the given game, up to the given number of actions,
+    /// searching each one a move shallower than the previous one
+    pub fn principal_variation(&mut self, game: &Game, length: usize) -> Vec<Action> {
+        let depth_limit = self.depth_limit;
+        let mut game = game.clone();
+        let mut variation = Vec::new();
+
+        while variation.len() < length && !game.is_over() {
+            let player = game.current_player();
+            let actions = game.actions(player).collect::<Vec<_>>();
+            if actions.is_empty() {
+                game = game.pass();
+                continue;
+            }
+
+            self.depth_limit = depth_limit.saturating_sub(variation.len() as u32);
+            let values = actions.into_iter()
+                .map(|action| (self.search(&game.result(&action)), action))
+                .collect::<Vec<_>>();
+            let (_, best) = match player {
+                Player::Bot => values.into_iter().max_by_key(|(value, _)| *value),
+                Player::Human => values.into_iter().min_by_key(|(value, _)| *value),
+            }.unwrap();
+
+            game = game.result(&best);
+            variation.push(best);
+        }
+
+        self.depth_limit = depth_limit;
+        variation
+    }
+
+    /// Checks if the deadline has passed, looking at the time only once in a while
+    fn is_out_of_time(&mut self) -> bool {
+        if !self.timed_out && self.num_nodes_expanded.is_multiple_of(DEADLINE_CHECK_INTERVAL) {
+            self.timed_out = self.deadline.is_some_and(|deadline| Instant::now() >= deadline);
+        }
+        self.timed_out
+    }
+
+    /// Returns the actions of the given player the given number of moves ahead that the search looks at.
+    /// A bot making mistakes overlooks some of them beyond its horizon, but never all of them.
+    fn actions(&mut self, game: &Game, player: Player, depth: u32) -> Vec<Action> {
+        let mut actions = game.actions(player).collect::<Vec<_>>();
+        let Some(mistakes) = self.mistakes else {
+            return actions;
+        };
+
+        // The actions come in no particular order, so they are sorted for the seed to give the same mistakes
+        actions.sort_by(|a, b| a.placement().cmp(b.placement()));
+        if depth <= mistakes.horizon || mistakes.oversight <= 0.0 {
+            return actions;
+        }
+
+        let seen = actions.iter()
+            .filter(|_| !self.rng.gen_bool(mistakes.oversight as f64))
+            .cloned()
+            .collect::<Vec<_>>();
+        if seen.is_empty() { actions } else { seen }
+    }
     
     /// Finds the min value of the minimax
     fn min_value(&mut self, game: Game, max_best: i32, mut min_best: i32, depth: u32) -> i32 {
         if game.is_over() {
             return game.utility();
-        } else if depth > self.depth_limit {
+        } else if depth > self.depth_limit || self.is_out_of_time() {
             return self.evaluate(game);
         }
 
         self.num_nodes_expanded += 1;
 
-        let mut min_best_here = max_best_evaluation();
+        let mut min_best_here = max_best_evaluation(game.board().size());
 
-        for act in game.actions(Player::Human) {
+        for act in self.actions(&game, Player::Human, depth) {
             let result = game.result(&act);
             let value = self.max_value(result, max_best, min_best, depth + 1);
             if value < min_best_here {
                 min_best_here = value;
             }
-            if min_best_here <= min_best {
+            // The bot already has a move worth max_best elsewhere, so it never lets the search get here
+            if min_best_here <= max_best {
                 return min_best_here;
             }
             min_best = min(min_best, min_best_here);
         }
 
-        return min_best_here;
+        min_best_here
     }
     
     /// Finds the max value of the minimax
     fn max_value(&mut self, game: Game, mut max_best: i32, min_best: i32, depth: u32) -> i32 {
         if game.is_over() {
             return game.utility();
-        } else if depth > self.depth_limit {
+        } else if depth > self.depth_limit || self.is_out_of_time() {
             return self.evaluate(game);
         }
 
         self.num_nodes_expanded += 1;
 
-        let mut max_best_here = min_best_evaluation();
+        let mut max_best_here = min_best_evaluation(game.board().size());
         
-        for act in game.actions(Player::Bot) {
+        for act in self.actions(&game, Player::Bot, depth) {
             let result = game.result(&act);
             let value = self.min_value(result, max_best, min_best, depth + 1);
             if value > max_best_here {
@@ -117,7 +358,7 @@ impl Bot {
             max_best = max(max_best, max_best_here);
         }
         
-        return max_best_here;
+        max_best_here
     }
     
     /// Evaluates the given game to a value
@@ -126,8 +367,104 @@ impl Bot {
             return self.minimax_cache.get(&game).copied().unwrap();
         }
         
-        let value = game.evaluate();
+        let value = match &self.weights {
+            Some(weights) => game.evaluate_with(weights),
+            None => game.evaluate(),
+        };
+        if self.minimax_cache.len() >= self.cache_size {
+            self.minimax_cache.clear();
+        }
         self.minimax_cache.insert(game, value);
         value
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::board::{Board, Position};
+    use crate::bot::{Bot, Mistakes};
+    use crate::game::{max_best_evaluation, min_best_evaluation, Game, Player};
+
+    /// Finds the value of the given game for the bot by searching every move to the given depth
+    fn minimax(game: &Game, depth: u32) -> i32 {
+        if game.is_over() {
+            return game.utility();
+        } else if depth == 0 {
+            return game.evaluate();
+        }
+
+        let player = game.current_player();
+        let values = game.actions(player).map(|action| minimax(&game.result(&action), depth - 1));
+        let size = game.board().size();
+        match player {
+            Player::Bot => values.max().unwrap_or(min_best_evaluation(size)),
+            Player::Human => values.min().unwrap_or(max_best_evaluation(size)),
+        }
+    }
+
+    #[test]
+    fn decide_minimax() {
+        // Cutting the replies off against the best value for the human instead of the bot's valued each move
+        // by whichever reply came first, which picked a move worth -107 or worse here in place of (5,3)
+        let board = Board::parse([
+            "EEEEEEEE",
+            "EEDLEEEE",
+            "EEELEEEE",
+            "EEDDDDEE",
+            "EEEDLEEE",
+            "EEDEELEE",
+            "EEEEEEEE",
+            "EEEEEEEE",
+        ].join("\n")).unwrap();
+        let game = Game::parse(board, Player::Bot);
+
+        let best = game.actions(Player::Bot).map(|action| minimax(&game.result(&action), 1)).max();
+        let (action, result) = Bot::new(1).decide(&game).unwrap();
+        assert_eq!(action.placement(), &Position::new(5, 3));
+        assert_eq!(Some(minimax(&result, 1)), best);
+    }
+
+    #[test]
+    fn mistakes() {
+        let decide = |game: &Game, mistakes: Mistakes| {
+            let mut bot = Bot::new(2);
+            bot.set_mistakes(mistakes);
+            let (_, result) = bot.decide(game).unwrap();
+            (bot.search(&result), result)
+        };
+
+        // Playing the first move in order a few times gives a position where the moves differ in value
+        let mut game = Game::parse(Board::new(), Player::Bot);
+        for _ in 0..5 {
+            let mut actions = game.actions(game.current_player()).collect::<Vec<_>>();
+            actions.sort_by(|a, b| a.placement().cmp(b.placement()));
+            game = game.result(&actions[0]);
+        }
+        let game = game.swap_colours();
+
+        let mistakes = Mistakes { temperature: 1000.0, oversight: 0.5, horizon: 0, seed: 1 };
+        assert!(decide(&game, mistakes).1 == decide(&game, mistakes).1);
+
+        // Deepening gives the move of the last depth, whatever the shallower depths drew
+        let mut bot = Bot::new(2);
+        bot.set_mistakes(mistakes);
+        let (_, deepened) = bot.decide_iteratively(&game, |_, _| {}).unwrap();
+        assert!(deepened == decide(&game, mistakes).1);
+        assert!(bot.decide(&game).unwrap().1 == deepened);
+
+        let results = (0..40)
+            .map(|seed| decide(&game, Mistakes { seed, ..mistakes }).1.board().clone())
+            .collect::<HashSet<_>>();
+        assert_eq!(results.len(), game.actions(Player::Bot).count());
+
+        let careful = Mistakes { temperature: 0.001, oversight: 0.0, horizon: 0, seed: 1 };
+        let best = game.actions(Player::Bot).map(|action| Bot::new(2).search(&game.result(&action))).max();
+        assert_eq!(Some(decide(&game, careful).0), best);
+
+        assert!(careful.validate().is_ok());
+        assert!(Mistakes { temperature: -1.0, ..careful }.validate().is_err());
+        assert!(Mistakes { oversight: 1.5, ..careful }.validate().is_err());
+    }
+}
@@ -0,0 +1,293 @@
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use rand::rngs::StdRng;
+use rand::seq::IteratorRandom;
+use rand::SeedableRng;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::board::Board;
+use crate::bot::Bot;
+use crate::errors::Error;
+use crate::errors::Error::InvalidConfig;
+use crate::game::{Game, Phase, Player, Variant};
+
+/// Number of moves played at random at the start of each self-play game, so that the games differ
+const RANDOM_PLIES: usize = 8;
+
+static CALIBRATION: OnceLock<Calibration> = OnceLock::new();
+
+/// Model of the outcome of a game from its evaluation in one phase of the game
+///
+/// The probabilities follow an ordered logistic model, where the bot wins with probability
+/// `sigmoid(scale * score - draw_width)` and loses with probability `sigmoid(-scale * score - draw_width)`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PhaseModel {
+    pub scale: f32,
+    pub draw_width: f32,
+    /// Final disk margin expected per point of evaluation
+    pub margin_per_score: f32,
+}
+
+/// Models of the outcome of a game from its evaluation for early, mid and end stage of the game
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Calibration {
+    pub phases: [PhaseModel; 3],
+}
+
+/// Chances of each outcome of a game for the bot, with its expected final disk margin
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Prediction {
+    pub win: f32,
+    pub draw: f32,
+    pub loss: f32,
+    /// Disks of the bot minus disks of the human at the end of the game
+    pub expected_margin: f32,
+}
+
+/// Outcome of a game for the bot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Win,
+    Draw,
+    Loss,
+}
+
+/// Position reached in a game, labelled with how the game ended
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub phase: Phase,
+    pub score: i32,
+    pub outcome: Outcome,
+    pub margin: i32,
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+impl Default for Calibration {
+    /// Fitted on 400 self-play games of intelligence 2
+    fn default() -> Self {
+        Self {
+            phases: [
+                PhaseModel { scale: 0.0004, draw_width: 0.1, margin_per_score: 0.0077 },
+                PhaseModel { scale: 0.0008, draw_width: 0.1, margin_per_score: 0.0115 },
+                PhaseModel { scale: 0.0025, draw_width: 0.1, margin_per_score: 0.024 },
+            ],
+        }
+    }
+}
+
+impl PhaseModel {
+
+    /// Returns the chances of each outcome for the bot from the given score on a board with the given number of squares
+    fn predict(&self, score: i32, squares: usize) -> Prediction {
+        let x = self.scale * score as f32;
+        let win = sigmoid(x - self.draw_width);
+        let loss = sigmoid(-x - self.draw_width);
+
+        Prediction {
+            win,
+            draw: (1.0 - win - loss).max(0.0),
+            loss,
+            expected_margin: (self.margin_per_score * score as f32).clamp(-(squares as f32), squares as f32),
+        }
+    }
+
+    /// Returns the log-likelihood of the outcomes of the given samples under this model
+    fn log_likelihood(&self, samples: &[&Sample]) -> f32 {
+        samples.iter()
+            .map(|sample| {
+                let prediction = self.predict(sample.score, usize::MAX);
+                let p = match sample.outcome {
+                    Outcome::Win => prediction.win,
+                    Outcome::Draw => prediction.draw,
+                    Outcome::Loss => prediction.loss,
+                };
+                p.max(f32::EPSILON).ln()
+            })
+            .sum()
+    }
+
+    /// Fits a model to the given samples, searching the scale and the draw width on a grid
+    fn fit(samples: &[&Sample]) -> Self {
+        let mut best = PhaseModel { scale: 0.0, draw_width: 0.0, margin_per_score: 0.0 };
+        let mut best_likelihood = f32::NEG_INFINITY;
+
+        for i in 0..=80 {
+            // Scales from 1e-5 to 1e-1
+            let scale = 10f32.powf(-5.0 + i as f32 / 20.0);
+
+            for j in 0..=40 {
+                let model = PhaseModel { scale, draw_width: j as f32 / 10.0, margin_per_score: 0.0 };
+                let likelihood = model.log_likelihood(samples);
+
+                if likelihood > best_likelihood {
+                    best_likelihood = likelihood;
+                    best = model;
+                }
+            }
+        }
+
+        // Least squares through the origin, since the evaluation is symmetric for both players
+        let covariance: f64 = samples.iter().map(|s| s.score as f64 * s.margin as f64).sum();
+        let variance: f64 = samples.iter().map(|s| (s.score as f64).powi(2)).sum();
+        if variance > 0.0 {
+            best.margin_per_score = (covariance / variance) as f32;
+        }
+
+        best
+    }
+}
+
+impl Calibration {
+
+    /// Returns the chances of each outcome for the bot in the given game
+    pub fn predict(&self, game: &Game) -> Prediction {
+        self.predict_score(game, game.evaluate())
+    }
+
+    /// Returns the chances of each outcome for the bot in the given game, from the given score of the game for the
+    /// bot, such as the value of a search instead of the evaluation
+    pub fn predict_score(&self, game: &Game, score: i32) -> Prediction {
+        let squares = game.board().size() * game.board().size();
+        let mut prediction = self.phases[game.phase().to_index()].predict(score, squares);
+
+        // Winning an anti game means ending with fewer disks
+        if game.variant() == Variant::Anti {
+            prediction.expected_margin = -prediction.expected_margin;
+        }
+        prediction
+    }
+
+    /// Fits the models of each phase to the given samples, keeping the default model of a phase without samples
+    pub fn fit(samples: &[Sample]) -> Self {
+        let mut calibration = Self::default();
+
+        for (index, model) in calibration.phases.iter_mut().enumerate() {
+            let samples = samples.iter()
+                .filter(|sample| sample.phase.to_index() == index)
+                .collect::<Vec<_>>();
+
+            if !samples.is_empty() {
+                *model = PhaseModel::fit(&samples);
+            }
+        }
+
+        calibration
+    }
+
+    /// Loads the calibration from the given JSON file
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let data = fs::read_to_string(path)
+            .map_err(|e| InvalidConfig(format!("Failed to read calibration file {}: {}", path.display(), e)))?;
+
+        serde_json::from_str(&data)
+            .map_err(|e| InvalidConfig(format!("Invalid calibration file {}: {}", path.display(), e)))
+    }
+}
+
+/// Replaces the default calibration with the given calibration
+///
+/// Pre-conditions:
+/// * No prediction has been made yet
+pub fn set_calibration(calibration: Calibration) -> Result<(), Error> {
+    CALIBRATION.set(calibration)
+        .map_err(|_| InvalidConfig("Calibration is already in use".to_string()))
+}
+
+/// Returns the calibration in use
+pub fn calibration() -> &'static Calibration {
+    CALIBRATION.get_or_init(Calibration::default)
+}
+
+/// Plays games of the bot against itself and labels every position reached with how its game ended.
+/// Both players search with the given intelligence after a few random moves chosen from the seed.
+pub fn self_play(num_games: usize, intelligence: u32, seed: u64) -> Vec<Sample> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut bot = Bot::new(intelligence);
+    let mut samples = Vec::new();
+
+    for _ in 0..num_games {
+        let mut game = Game::parse(Board::new(), Player::Human);
+        let mut positions = Vec::new();
+
+        while !game.is_over() {
+            let player = game.current_player();
+            if game.actions(player).next().is_none() {
+                game = game.pass();
+                continue;
+            }
+
+            let action = if positions.len() < RANDOM_PLIES {
+                game.actions(player).choose(&mut rng).unwrap()
+            } else {
+                bot.principal_variation(&game, 1).remove(0)
+            };
+            game = game.result(&action);
+            positions.push((game.phase(), game.evaluate()));
+        }
+
+        let margin = disk_margin(&game);
+        let outcome = match game.winner() {
+            Some(Player::Bot) => Outcome::Win,
+            Some(Player::Human) => Outcome::Loss,
+            None => Outcome::Draw,
+        };
+
+        samples.extend(positions.into_iter()
+            .map(|(phase, score)| Sample { phase, score, outcome, margin }));
+    }
+
+    samples
+}
+
+/// Returns the disks of the bot minus the disks of the human
+fn disk_margin(game: &Game) -> i32 {
+    game.board().positions(Player::Bot.disk()).count() as i32 -
+        game.board().positions(Player::Human.disk()).count() as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::calibration::{Calibration, Outcome, Sample, self_play};
+    use crate::game::{Game, Phase, Player};
+
+    #[test]
+    fn predict() {
+        let calibration = Calibration::default();
+
+        let prediction = calibration.predict(&Game::parse(Board::new(), Player::Human));
+        assert!((prediction.win - prediction.loss).abs() < 1e-6);
+        assert!((prediction.win + prediction.draw + prediction.loss - 1.0).abs() < 1e-6);
+        assert_eq!(prediction.expected_margin, 0.0);
+    }
+
+    #[test]
+    fn fit() {
+        let samples = self_play(2, 1, 0);
+        assert!(!samples.is_empty());
+        assert!(samples.iter().any(|sample| sample.phase == Phase::End));
+
+        // Higher scores win more often
+        let samples = (-50..50)
+            .flat_map(|i| {
+                let outcome = if i > 5 { Outcome::Win } else if i < -5 { Outcome::Loss } else { Outcome::Draw };
+                [Phase::Early, Phase::Mid, Phase::End].map(|phase| Sample { phase, score: i * 10, outcome, margin: i })
+            })
+            .collect::<Vec<_>>();
+
+        let calibration = Calibration::fit(&samples);
+        for model in calibration.phases {
+            assert!(model.draw_width > 0.0);
+            assert!((model.margin_per_score - 0.1).abs() < 1e-3);
+            let prediction = model.predict(300, 64);
+            assert!(prediction.win > 0.9);
+            assert!(prediction.loss < 0.05);
+        }
+    }
+}
@@ -0,0 +1,331 @@
+use std::cmp::{max, min};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::board::Disk;
+use crate::errors::Error;
+use crate::errors::Error::InvalidArgument;
+use crate::game::Player;
+
+/// Longest time a player can have for a game, or gain per move, which is a week
+const MAX_TIME_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+
+/// Rules for how much time each player has to make their moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum TimeControl {
+    /// Each player starts with the initial time and gains the increment after every move
+    Fischer { initial_ms: u64, increment_ms: u64 },
+    /// Each player has a main time, after which every move must be made within a period.
+    /// Exceeding a period uses it up, and the player loses when no periods are left.
+    ByoYomi { main_ms: u64, period_ms: u64, periods: u32 },
+}
+
+impl TimeControl {
+
+    /// Checks if a game can be played with this time control
+    fn validate(&self) -> Result<(), Error> {
+        match *self {
+            TimeControl::Fischer { initial_ms: 0, .. } =>
+                Err(InvalidArgument("The initial time must be positive".to_string())),
+            TimeControl::ByoYomi { main_ms: 0, periods: 0, .. } =>
+                Err(InvalidArgument("Byo-yomi needs a main time or at least one period".to_string())),
+            TimeControl::ByoYomi { period_ms: 0, periods, .. } if periods > 0 =>
+                Err(InvalidArgument("The byo-yomi period must be positive".to_string())),
+            TimeControl::Fischer { initial_ms, increment_ms }
+                if initial_ms > MAX_TIME_MS || increment_ms > MAX_TIME_MS =>
+                Err(InvalidArgument(format!("Times can't exceed {} ms", MAX_TIME_MS))),
+            TimeControl::ByoYomi { main_ms, period_ms, periods }
+                if main_ms as u128 + period_ms as u128 * periods as u128 > MAX_TIME_MS as u128 =>
+                Err(InvalidArgument(format!("The total time can't exceed {} ms", MAX_TIME_MS))),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Time a player has left
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TimeLeft {
+    /// Remaining main time, or the whole time for Fischer
+    main: Duration,
+    /// Byo-yomi periods left
+    periods: u32,
+    /// Remaining time of the current byo-yomi period
+    period: Duration,
+}
+
+/// Remaining time of one player, as sent to the clients
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PlayerClock {
+    pub side: Disk,
+    /// Remaining main time, or the time left in the current period once it ran out
+    pub remaining_ms: u64,
+    /// Byo-yomi periods left, which is null for Fischer
+    pub periods: Option<u32>,
+}
+
+/// State of the clock of a game, as sent to the clients
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ClockState {
+    pub control: TimeControl,
+    /// Clocks of the players, dark first
+    pub players: Vec<PlayerClock>,
+    /// Side whose clock is running
+    pub running: Option<Disk>,
+    /// Side that ran out of time
+    pub flagged: Option<Disk>,
+}
+
+/// Chess clock of the two players of a game
+#[derive(Debug, Clone)]
+pub struct Clock {
+    control: TimeControl,
+    times: HashMap<Player, TimeLeft>,
+    running: Option<(Player, Instant)>,
+    flagged: Option<Player>,
+}
+
+impl Clock {
+
+    /// Creates a new stopped clock giving both players the full time of the given control
+    pub fn new(control: TimeControl) -> Result<Self, Error> {
+        control.validate()?;
+
+        let time = match control {
+            TimeControl::Fischer { initial_ms, .. } => TimeLeft {
+                main: Duration::from_millis(initial_ms),
+                periods: 0,
+                period: Duration::ZERO,
+            },
+            TimeControl::ByoYomi { main_ms, period_ms, periods } => TimeLeft {
+                main: Duration::from_millis(main_ms),
+                periods,
+                period: Duration::from_millis(period_ms),
+            },
+        };
+
+        Ok(Self {
+            control,
+            times: HashMap::from([(Player::Human, time), (Player::Bot, time)]),
+            running: None,
+            flagged: None,
+        })
+    }
+
+    /// Returns the player who ran out of time
+    pub fn flagged(&self) -> Option<Player> {
+        self.flagged
+    }
+
+    /// Starts the clock of the given player
+    pub fn start(&mut self, player: Player, now: Instant) {
+        if self.flagged.is_none() {
+            self.running = Some((player, now));
+        }
+    }
+
+    /// Stops the running clock after its player moved, adding the increment or resetting the period.
+    /// Returns false when the player ran out of time before moving.
+    pub fn stop(&mut self, now: Instant) -> bool {
+        let Some((player, started)) = self.running.take() else {
+            return self.flagged.is_none();
+        };
+
+        match self.spend(player, now.saturating_duration_since(started)) {
+            Some(mut time) => {
+                match self.control {
+                    TimeControl::Fischer { increment_ms, .. } => time.main += Duration::from_millis(increment_ms),
+                    TimeControl::ByoYomi { period_ms, .. } => time.period = Duration::from_millis(period_ms),
+                }
+                self.times.insert(player, time);
+                true
+            },
+            None => {
+                self.flag(player);
+                false
+            },
+        }
+    }
+
+    /// Flags the running player if their time is up, returning them the first time only
+    pub fn check(&mut self, now: Instant) -> Option<Player> {
+        let (player, started) = self.running?;
+
+        if self.spend(player, now.saturating_duration_since(started)).is_none() {
+            self.running = None;
+            self.flag(player);
+            return Some(player);
+        }
+        None
+    }
+
+    /// Returns how long until the running player runs out of time
+    pub fn until_flag(&self, now: Instant) -> Option<Duration> {
+        let (player, started) = self.running?;
+        let elapsed = now.saturating_duration_since(started);
+        let time = self.times[&player];
+
+        let total = time.main.saturating_add(time.period.saturating_mul(time.periods));
+        Some(total.saturating_sub(elapsed))
+    }
+
+    /// Returns the time the given player should spend on the current move when the board has the given
+    /// number of empty squares, keeping enough time for the rest of the game
+    pub fn budget(&self, player: Player, now: Instant, empties: usize) -> Duration {
+        let time = match self.running {
+            Some((running, started)) if running == player =>
+                self.spend(player, now.saturating_duration_since(started)),
+            _ => Some(self.times[&player]),
+        };
+        let Some(time) = time else {
+            return Duration::ZERO;
+        };
+
+        // The player makes about half of the remaining moves
+        let moves_left = max(empties / 2, 1) as u32;
+        let share = time.main / moves_left;
+
+        match self.control {
+            TimeControl::Fischer { increment_ms, .. } => {
+                let increment = Duration::from_millis(increment_ms);
+                min(share + increment * 3 / 4, time.main * 9 / 10)
+            },
+            TimeControl::ByoYomi { .. } if time.periods == 0 => min(share, time.main * 9 / 10),
+            TimeControl::ByoYomi { .. } => share + time.period * 3 / 4,
+        }
+    }
+
+    /// Returns the state of the clock at the given time
+    pub fn state(&self, now: Instant) -> ClockState {
+        let players = [Player::Human, Player::Bot].into_iter()
+            .map(|side| {
+                let time = match self.running {
+                    Some((player, started)) if player == side =>
+                        self.spend(side, now.saturating_duration_since(started)),
+                    _ if self.flagged == Some(side) => None,
+                    _ => Some(self.times[&side]),
+                };
+                let remaining = time.map_or(Duration::ZERO, |time| {
+                    if time.main.is_zero() && time.periods > 0 { time.period } else { time.main }
+                });
+
+                PlayerClock {
+                    side: side.disk(),
+                    remaining_ms: remaining.as_millis() as u64,
+                    periods: match self.control {
+                        TimeControl::Fischer { .. } => None,
+                        TimeControl::ByoYomi { .. } => Some(time.map_or(0, |time| time.periods)),
+                    },
+                }
+            })
+            .collect();
+
+        ClockState {
+            control: self.control,
+            players,
+            running: self.running.map(|(player, _)| player.disk()),
+            flagged: self.flagged.map(|player| player.disk()),
+        }
+    }
+
+    /// Returns the time the given player would have left after thinking for the elapsed time,
+    /// or None if they would run out of time
+    fn spend(&self, player: Player, elapsed: Duration) -> Option<TimeLeft> {
+        let time = self.times[&player];
+
+        if elapsed < time.main {
+            return Some(TimeLeft { main: time.main - elapsed, ..time });
+        }
+
+        let overtime = elapsed - time.main;
+        let period_nanos = time.period.as_nanos();
+        if period_nanos == 0 {
+            return None;
+        }
+
+        let used = overtime.as_nanos() / period_nanos;
+        if used >= time.periods as u128 {
+            return None;
+        }
+
+        Some(TimeLeft {
+            main: Duration::ZERO,
+            periods: time.periods - used as u32,
+            period: time.period - Duration::from_nanos((overtime.as_nanos() % period_nanos) as u64),
+        })
+    }
+
+    /// Marks the given player as having run out of time
+    fn flag(&mut self, player: Player) {
+        self.flagged = Some(player);
+        self.times.insert(player, TimeLeft { main: Duration::ZERO, periods: 0, period: Duration::ZERO });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::clock::{Clock, TimeControl};
+    use crate::game::Player::{Bot, Human};
+
+    #[test]
+    fn fischer() {
+        let mut clock = Clock::new(TimeControl::Fischer { initial_ms: 1000, increment_ms: 500 }).unwrap();
+        let now = Instant::now();
+
+        clock.start(Human, now);
+        assert!(clock.stop(now + Duration::from_millis(800)));
+        assert_eq!(clock.state(now).players[0].remaining_ms, 700);
+        assert_eq!(clock.state(now).players[0].periods, None);
+
+        clock.start(Bot, now);
+        assert_eq!(clock.state(now + Duration::from_millis(300)).players[1].remaining_ms, 700);
+        assert_eq!(clock.check(now + Duration::from_millis(999)), None);
+        assert_eq!(clock.check(now + Duration::from_millis(1000)), Some(Bot));
+        assert_eq!(clock.check(now + Duration::from_millis(2000)), None);
+        assert_eq!(clock.flagged(), Some(Bot));
+
+        assert!(Clock::new(TimeControl::Fischer { initial_ms: 0, increment_ms: 500 }).is_err());
+        assert!(Clock::new(TimeControl::Fischer { initial_ms: 1000, increment_ms: u64::MAX }).is_err());
+        assert!(Clock::new(TimeControl::ByoYomi { main_ms: 0, period_ms: u64::MAX, periods: u32::MAX }).is_err());
+        assert!(Clock::new(TimeControl::ByoYomi { main_ms: 0, period_ms: 60_000, periods: 5 }).is_ok());
+    }
+
+    #[test]
+    fn byo_yomi() {
+        let mut clock = Clock::new(TimeControl::ByoYomi { main_ms: 1000, period_ms: 300, periods: 2 }).unwrap();
+        let now = Instant::now();
+
+        // Uses up the main time and one period
+        clock.start(Human, now);
+        assert!(clock.stop(now + Duration::from_millis(1400)));
+        let state = clock.state(now);
+        assert_eq!(state.players[0].remaining_ms, 300);
+        assert_eq!(state.players[0].periods, Some(1));
+
+        // Moves within the period keep it
+        clock.start(Human, now);
+        assert!(clock.stop(now + Duration::from_millis(299)));
+        assert_eq!(clock.state(now).players[0].periods, Some(1));
+
+        clock.start(Human, now);
+        assert_eq!(clock.until_flag(now), Some(Duration::from_millis(300)));
+        assert!(!clock.stop(now + Duration::from_millis(300)));
+        assert_eq!(clock.flagged(), Some(Human));
+    }
+
+    #[test]
+    fn budget() {
+        let clock = Clock::new(TimeControl::Fischer { initial_ms: 60_000, increment_ms: 0 }).unwrap();
+        let now = Instant::now();
+        assert_eq!(clock.budget(Bot, now, 60), Duration::from_secs(2));
+        assert_eq!(clock.budget(Bot, now, 1), Duration::from_secs(54));
+
+        let clock = Clock::new(TimeControl::ByoYomi { main_ms: 0, period_ms: 4000, periods: 1 }).unwrap();
+        assert_eq!(clock.budget(Bot, now, 60), Duration::from_secs(3));
+    }
+}
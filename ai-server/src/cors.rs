@@ -0,0 +1,325 @@
+use std::io::Cursor;
+use std::str::FromStr;
+
+use itertools::Itertools;
+use regex::Regex;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{Header, Method, Status};
+use rocket::{Request, Response};
+
+use crate::errors::Error;
+use crate::errors::Error::InvalidConfig;
+use crate::settings::CorsSettings;
+
+pub const ANY_ORIGIN: &str = "*";
+pub const REGEX_PREFIX: &str = "regex:";
+pub const WILDCARD_CHAR: char = '*';
+pub const ANY_HEADER: &str = "*";
+
+/// Origin that is allowed to make cross-origin requests
+#[derive(Debug)]
+pub enum AllowedOrigin {
+    Any,
+    Exact(String),
+    Pattern(Regex),
+}
+
+impl AllowedOrigin {
+
+    /// Parses the given string into an allowed origin
+    ///
+    /// A lone '*' allows every origin, a string prefixed with "regex:" is matched as a regex and any other
+    /// '*' matches a run of characters within the host
+    pub fn parse(s: &str) -> Result<Self, Error> {
+        if s == ANY_ORIGIN {
+            return Ok(AllowedOrigin::Any);
+        }
+
+        let pattern = if let Some(regex) = s.strip_prefix(REGEX_PREFIX) {
+            format!("^(?:{})$", regex)
+        } else if s.contains(WILDCARD_CHAR) {
+            format!("^{}$", s.split(WILDCARD_CHAR)
+                .map(regex::escape)
+                .collect::<Vec<String>>()
+                .join("[^/]*"))
+        } else {
+            return Ok(AllowedOrigin::Exact(s.to_string()));
+        };
+
+        Regex::new(&pattern)
+            .map(AllowedOrigin::Pattern)
+            .map_err(|e| InvalidConfig(format!("Invalid allowed origin {}: {}", s, e)))
+    }
+
+    /// Checks if the given origin is allowed by this
+    pub fn matches(&self, origin: &str) -> bool {
+        match self {
+            AllowedOrigin::Any => true,
+            AllowedOrigin::Exact(allowed) => allowed == origin,
+            AllowedOrigin::Pattern(regex) => regex.is_match(origin),
+        }
+    }
+}
+
+pub struct Cors {
+    allowed_origins: Vec<AllowedOrigin>,
+    allowed_methods: Vec<Method>,
+    allowed_headers: Vec<String>,
+    max_age: u64,
+    allow_credentials: bool,
+}
+
+impl Cors {
+
+    /// Creates a new CORS fairing from the given settings
+    pub fn new(settings: &CorsSettings) -> Result<Cors, Error> {
+        let allowed_origins = settings.allowed_origins.iter()
+            .map(|s| AllowedOrigin::parse(s))
+            .collect::<Result<Vec<AllowedOrigin>, Error>>()?;
+
+        let allowed_methods = settings.allowed_methods.iter()
+            .map(|s| Method::from_str(&s.to_uppercase())
+                .map_err(|_| InvalidConfig(format!("Invalid allowed method: {}", s))))
+            .collect::<Result<Vec<Method>, Error>>()?;
+
+        for header in settings.allowed_headers.iter() {
+            if header.is_empty() || header.contains(|c: char| c.is_whitespace() || c == ',') {
+                return Err(InvalidConfig(format!("Invalid allowed header: {:?}", header)));
+            }
+        }
+
+        Ok(Cors {
+            allowed_origins,
+            allowed_methods,
+            allowed_headers: settings.allowed_headers.clone(),
+            max_age: settings.max_age,
+            allow_credentials: settings.allow_credentials,
+        })
+    }
+
+    /// Checks if the given origin is allowed to make cross-origin requests
+    pub fn is_allowed(&self, origin: &str) -> bool {
+        self.allowed_origins.iter().any(|allowed| allowed.matches(origin))
+    }
+
+    /// Checks if the given request is a CORS preflight request
+    fn is_preflight(request: &Request<'_>) -> bool {
+        request.method() == Method::Options
+            && request.headers().contains("Origin")
+            && request.headers().contains("Access-Control-Request-Method")
+    }
+
+    /// Returns the value of Access-Control-Allow-Headers for the given request
+    fn allowed_headers(&self, request: &Request<'_>) -> String {
+        if self.allowed_headers.iter().any(|h| h == ANY_HEADER) {
+            // Browsers take '*' literally for requests with credentials, so echo the requested headers instead
+            return request.headers().get_one("Access-Control-Request-Headers")
+                .unwrap_or(ANY_HEADER)
+                .to_string();
+        }
+
+        self.allowed_headers.join(", ")
+    }
+
+    /// Checks if a route is mounted for the given method at the path of the given request
+    fn is_routed(request: &Request<'_>, method: Method) -> bool {
+        let segments = request.uri().path().segments().collect::<Vec<&str>>();
+        request.rocket().routes()
+            .filter(|route| route.method == method)
+            .any(|route| matches_path(route.uri.path(), &segments))
+    }
+
+    /// Answers the given preflight request, returning whether the request it precedes is allowed.
+    /// Preflight requests for paths without a route for the requested method are left to fail like any other request.
+    fn preflight(&self, request: &Request<'_>, response: &mut Response<'_>) -> bool {
+        let method = request.headers().get_one("Access-Control-Request-Method")
+            .and_then(|m| Method::from_str(m).ok());
+
+        let Some(method) = method.filter(|m| self.allowed_methods.contains(m)) else {
+            response.set_status(Status::Forbidden);
+            return false;
+        };
+        if !Self::is_routed(request, method) {
+            return false;
+        }
+
+        response.set_status(Status::NoContent);
+        response.remove_header("Content-Type");
+        response.set_sized_body(0, Cursor::new(""));
+
+        response.set_header(Header::new("Access-Control-Allow-Headers", self.allowed_headers(request)));
+        response.set_header(Header::new("Access-Control-Max-Age", self.max_age.to_string()));
+        true
+    }
+}
+
+/// Checks if the path of a route matches the given segments of a request path, where `<name>` matches any one
+/// segment and `<name..>` matches the rest of them
+fn matches_path(route: &str, segments: &[&str]) -> bool {
+    let mut segments = segments.iter();
+
+    for pattern in route.split('/').filter(|pattern| !pattern.is_empty()) {
+        if pattern.starts_with('<') && pattern.ends_with("..>") {
+            return true;
+        }
+        match segments.next() {
+            Some(segment) if pattern.starts_with('<') || pattern == *segment => {},
+            _ => return false,
+        }
+    }
+
+    segments.next().is_none()
+}
+
+#[rocket::async_trait]
+impl Fairing for Cors {
+    fn info(&self) -> Info {
+        Info {
+            name: "CORS Fairing",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let origin = match request.headers().get_one("Origin") {
+            Some(origin) => origin,
+            None => return,
+        };
+
+        // The headers depend on the origin, so caches must not share the response between origins
+        response.adjoin_header(Header::new("Vary", "Origin"));
+
+        if !self.is_allowed(origin) {
+            return;
+        }
+
+        if Self::is_preflight(request) && !self.preflight(request, response) {
+            return;
+        }
+
+        response.set_header(Header::new("Access-Control-Allow-Origin", origin));
+        response.set_header(Header::new("Access-Control-Allow-Methods",
+                                        self.allowed_methods.iter().map(|m| m.as_str()).join(", ")));
+        if self.allow_credentials {
+            response.set_header(Header::new("Access-Control-Allow-Credentials", "true"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::http::{Header, Status};
+    use rocket::local::blocking::Client;
+
+    use rocket_okapi::settings::OpenApiSettings;
+
+    use crate::api::{BASE, routes};
+    use crate::cors::{Cors, matches_path};
+    use crate::engine::Engine;
+    use crate::settings::{CorsSettings, EngineSettings};
+
+    #[test]
+    fn is_allowed() {
+        let cors = Cors::new(&CorsSettings {
+            allowed_origins: vec![
+                "http://localhost:8080".to_string(),
+                "https://*.seoulsky.org".to_string(),
+                "regex:https?://desdemona\\.seoulsky\\.dev(:\\d+)?".to_string(),
+            ],
+            ..CorsSettings::default()
+        }).unwrap();
+
+        assert!(cors.is_allowed("http://localhost:8080"));
+        assert!(!cors.is_allowed("http://localhost:8081"));
+
+        assert!(cors.is_allowed("https://desdemona.seoulsky.org"));
+        assert!(!cors.is_allowed("https://seoulsky.org"));
+        assert!(!cors.is_allowed("https://evil.com/.seoulsky.org"));
+
+        assert!(cors.is_allowed("http://desdemona.seoulsky.dev"));
+        assert!(cors.is_allowed("https://desdemona.seoulsky.dev:443"));
+        assert!(!cors.is_allowed("https://desdemona.seoulsky.dev.evil.com"));
+
+        let cors = Cors::new(&CorsSettings {
+            allowed_origins: vec!["*".to_string()],
+            ..CorsSettings::default()
+        }).unwrap();
+        assert!(cors.is_allowed("https://anything.com"));
+
+        assert!(Cors::new(&CorsSettings {
+            allowed_origins: vec!["regex:(".to_string()],
+            ..CorsSettings::default()
+        }).is_err());
+    }
+
+    #[test]
+    fn paths() {
+        assert!(matches_path("/api/v2/games/<id>/moves", &["api", "v2", "games", "abc", "moves"]));
+        assert!(!matches_path("/api/v2/games/<id>/moves", &["api", "v2", "games", "abc"]));
+        assert!(!matches_path("/api/v2/games/<id>", &["api", "v2", "games", "abc", "moves"]));
+        assert!(matches_path("/static/<path..>", &["static", "js", "app.js"]));
+        assert!(!matches_path("/api/decide", &["api", "evaluate"]));
+    }
+
+    #[test]
+    fn preflight() {
+        let cors = Cors::new(&CorsSettings {
+            allowed_origins: vec!["http://localhost:8080".to_string()],
+            allowed_methods: vec!["GET".to_string(), "POST".to_string()],
+            max_age: 600,
+            ..CorsSettings::default()
+        }).unwrap();
+
+        let rocket = rocket::build()
+            .mount(BASE, routes(&OpenApiSettings::default()).0)
+            .manage(Engine::new(EngineSettings::default()).unwrap())
+            .attach(cors);
+        let client = Client::tracked(rocket).unwrap();
+
+        let response = client.options("/api/v2/decide")
+            .header(Header::new("Origin", "http://localhost:8080"))
+            .header(Header::new("Access-Control-Request-Method", "POST"))
+            .header(Header::new("Access-Control-Request-Headers", "content-type"))
+            .dispatch();
+        assert_eq!(response.status(), Status::NoContent);
+        let headers = response.headers();
+        assert_eq!(headers.get_one("Access-Control-Allow-Origin"), Some("http://localhost:8080"));
+        assert_eq!(headers.get_one("Access-Control-Allow-Methods"), Some("GET, POST"));
+        assert_eq!(headers.get_one("Access-Control-Allow-Headers"), Some("content-type"));
+        assert_eq!(headers.get_one("Access-Control-Max-Age"), Some("600"));
+        assert_eq!(headers.get_one("Vary"), Some("Origin"));
+
+        let response = client.options("/api/v2/decide")
+            .header(Header::new("Origin", "http://localhost:8080"))
+            .header(Header::new("Access-Control-Request-Method", "DELETE"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+        assert_eq!(response.headers().get_one("Access-Control-Allow-Origin"), None);
+        assert_eq!(response.headers().get_one("Access-Control-Allow-Methods"), None);
+
+        // Only mounted routes are answered, so a missing route still fails
+        let response = client.options("/api/v2/missing")
+            .header(Header::new("Origin", "http://localhost:8080"))
+            .header(Header::new("Access-Control-Request-Method", "POST"))
+            .dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+        assert_eq!(response.headers().get_one("Access-Control-Allow-Origin"), None);
+
+        let response = client.options("/api/v2/decide")
+            .header(Header::new("Origin", "http://localhost:8080"))
+            .header(Header::new("Access-Control-Request-Method", "GET"))
+            .dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+
+        let response = client.options("/api/v2/decide")
+            .header(Header::new("Origin", "https://evil.com"))
+            .header(Header::new("Access-Control-Request-Method", "POST"))
+            .dispatch();
+        assert_eq!(response.headers().get_one("Access-Control-Allow-Origin"), None);
+
+        assert!(Cors::new(&CorsSettings {
+            allowed_methods: vec!["FETCH".to_string()],
+            ..CorsSettings::default()
+        }).is_err());
+    }
+}
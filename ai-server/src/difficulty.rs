@@ -0,0 +1,211 @@
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::calibration::Prediction;
+use crate::errors::Error;
+use crate::errors::Error::InvalidConfig;
+use crate::tournament::{EngineConfig, Record, run_tournament, score_to_elo};
+
+/// Elo rating given to the bot of intelligence 0, which the ratings of the other levels are measured from
+pub const ANCHOR_ELO: f64 = 800.0;
+
+/// Lead in the chances of winning over losing at which an adaptive bot changes its intelligence by a level
+const ADAPT_THRESHOLD: f32 = 0.3;
+
+static RATINGS: OnceLock<Ratings> = OnceLock::new();
+
+/// Estimated Elo ratings of the bot at each intelligence
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ratings {
+    /// Rating of each intelligence from 0, where the intelligences above the last one keep gaining its last step
+    pub elo: Vec<f64>,
+}
+
+/// Strength of the bot at an intelligence
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Difficulty {
+    pub intelligence: u32,
+    /// Estimated Elo rating of the bot
+    pub elo: f64,
+}
+
+impl Default for Ratings {
+    /// Measured with `desdemona rate 100 4`, from 200 games of each intelligence against the one below it.
+    /// Each step is within about ±50 Elo with 95% confidence, and the errors of the steps add up along the chain,
+    /// to about ±100 Elo at intelligence 4.
+    fn default() -> Self {
+        Self { elo: vec![800.0, 962.0, 1043.0, 1110.0, 1150.0] }
+    }
+}
+
+impl Ratings {
+
+    /// Returns the estimated rating of the bot of the given intelligence
+    pub fn elo(&self, intelligence: u32) -> f64 {
+        let index = intelligence as usize;
+        if let Some(elo) = self.elo.get(index) {
+            return *elo;
+        }
+
+        let last = self.elo.len() - 1;
+        let step = if last > 0 { self.elo[last] - self.elo[last - 1] } else { 0.0 };
+        self.elo[last] + step * (index - last) as f64
+    }
+
+    /// Returns the intelligence up to the given maximum whose rating is the closest to the given one
+    pub fn intelligence_for(&self, elo: f64, max_intelligence: u32) -> u32 {
+        (0..=max_intelligence)
+            .min_by(|a, b| (self.elo(*a) - elo).abs().total_cmp(&(self.elo(*b) - elo).abs()))
+            .unwrap()
+    }
+
+    /// Returns the difficulties of every intelligence up to the given maximum
+    pub fn difficulties(&self, max_intelligence: u32) -> Vec<Difficulty> {
+        (0..=max_intelligence)
+            .map(|intelligence| Difficulty { intelligence, elo: self.elo(intelligence) })
+            .collect()
+    }
+
+    /// Loads the ratings from the given JSON file
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let data = fs::read_to_string(path)
+            .map_err(|e| InvalidConfig(format!("Failed to read ratings file {}: {}", path.display(), e)))?;
+
+        let ratings: Self = serde_json::from_str(&data)
+            .map_err(|e| InvalidConfig(format!("Invalid ratings file {}: {}", path.display(), e)))?;
+        ratings.validate()?;
+        Ok(ratings)
+    }
+
+    /// Checks that there is a rating, and that the ratings are finite and never decrease.
+    /// Levels searching deeper must not be rated weaker, or picking a level by rating would skip around.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.elo.is_empty() {
+            return Err(InvalidConfig("Ratings must not be empty".to_string()));
+        }
+        if !self.elo.iter().all(|elo| elo.is_finite()) || self.elo.windows(2).any(|pair| pair[1] < pair[0]) {
+            return Err(InvalidConfig(format!("Ratings must be finite and never decrease: {:?}", self.elo)));
+        }
+        Ok(())
+    }
+}
+
+/// Replaces the default ratings with the given ratings
+///
+/// Pre-conditions:
+/// * No rating has been looked up yet
+pub fn set_ratings(ratings: Ratings) -> Result<(), Error> {
+    RATINGS.set(ratings)
+        .map_err(|_| InvalidConfig("Ratings are already in use".to_string()))
+}
+
+/// Returns the ratings in use
+pub fn ratings() -> &'static Ratings {
+    RATINGS.get_or_init(Ratings::default)
+}
+
+/// Rates every intelligence up to the given maximum by playing the given number of pairs of games against the
+/// intelligence below it as the reference, starting from intelligence 0 at the anchor rating
+pub fn rate(pairs: usize, max_intelligence: u32, threads: usize, seed: u64) -> Result<Ratings, Error> {
+    let config = |intelligence: u32| EngineConfig { name: intelligence.to_string(), intelligence, weights: None };
+    let mut elo = vec![ANCHOR_ELO];
+
+    for intelligence in 1..=max_intelligence {
+        let (engine, reference) = (config(intelligence), config(intelligence - 1));
+        let games = run_tournament(&engine, &reference, pairs, threads, seed, |_| true)?;
+
+        // Winning or losing every game gives no finite difference, so it counts as half a game less
+        let record = Record::of(&engine.name, &games);
+        let margin = 0.5 / record.games() as f64;
+        let score = record.score().clamp(margin, 1.0 - margin);
+        // A level measured weaker than the one below it is noise, as it searches deeper, so it is rated the same
+        elo.push(elo.last().unwrap() + score_to_elo(score).max(0.0));
+    }
+
+    Ok(Ratings { elo })
+}
+
+/// Intelligence of a bot that follows how well the human it plays is doing, getting weaker while it leads and
+/// stronger while it trails
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Adaptive {
+    intelligence: u32,
+    max_intelligence: u32,
+}
+
+impl Adaptive {
+
+    /// Creates a new adaptive intelligence starting at the given one
+    pub fn new(intelligence: u32, max_intelligence: u32) -> Self {
+        Self { intelligence: intelligence.min(max_intelligence), max_intelligence }
+    }
+
+    pub fn intelligence(&self) -> u32 {
+        self.intelligence
+    }
+
+    /// Changes the intelligence by a level from the chances of the bot after a move of the human
+    pub fn adapt(&mut self, prediction: &Prediction) {
+        let lead = prediction.win - prediction.loss;
+
+        if lead > ADAPT_THRESHOLD {
+            self.intelligence = self.intelligence.saturating_sub(1);
+        } else if lead < -ADAPT_THRESHOLD {
+            self.intelligence = (self.intelligence + 1).min(self.max_intelligence);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calibration::Prediction;
+    use crate::difficulty::{Adaptive, ANCHOR_ELO, rate, Ratings};
+
+    #[test]
+    fn ratings() {
+        let ratings = Ratings { elo: vec![800.0, 1000.0, 1100.0] };
+        assert_eq!(ratings.elo(1), 1000.0);
+        assert_eq!(ratings.elo(4), 1300.0);
+        assert_eq!(ratings.intelligence_for(1040.0, 8), 1);
+        assert_eq!(ratings.intelligence_for(2000.0, 3), 3);
+        assert_eq!(ratings.difficulties(2).len(), 3);
+
+        let defaults = Ratings::default();
+        assert!(defaults.validate().is_ok());
+        assert!(defaults.elo.windows(2).all(|pair| pair[0] < pair[1]));
+
+        assert!(Ratings { elo: vec![] }.validate().is_err());
+        assert!(Ratings { elo: vec![800.0, 900.0, 850.0] }.validate().is_err());
+        assert!(Ratings { elo: vec![800.0, f64::NAN] }.validate().is_err());
+        assert!(Ratings { elo: vec![800.0, 900.0, 900.0] }.validate().is_ok());
+        assert_eq!(defaults.elo[0], ANCHOR_ELO);
+    }
+
+    #[test]
+    fn rate_levels() {
+        let ratings = rate(1, 1, 2, 0).unwrap();
+        assert_eq!(ratings.elo.len(), 2);
+        assert_eq!(ratings.elo[0], ANCHOR_ELO);
+        assert!(ratings.validate().is_ok());
+    }
+
+    #[test]
+    fn adapt() {
+        let prediction = |win: f32, loss: f32| Prediction { win, draw: 1.0 - win - loss, loss, expected_margin: 0.0 };
+        let mut adaptive = Adaptive::new(3, 4);
+
+        adaptive.adapt(&prediction(0.8, 0.1));
+        assert_eq!(adaptive.intelligence(), 2);
+        adaptive.adapt(&prediction(0.4, 0.4));
+        assert_eq!(adaptive.intelligence(), 2);
+
+        for _ in 0..5 {
+            adaptive.adapt(&prediction(0.1, 0.8));
+        }
+        assert_eq!(adaptive.intelligence(), 4);
+    }
+}
@@ -0,0 +1,187 @@
+use std::cmp::min;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rocket::tokio::sync::Semaphore;
+use rocket::tokio::task;
+
+use crate::analysis::{analyze, GameReview, MoveAnalysis, review};
+use crate::board::Position;
+use crate::book::Book;
+use crate::bot::{Bot, Mistakes};
+use crate::calibration::{Calibration, set_calibration};
+use crate::difficulty::{Difficulty, ratings, Ratings, set_ratings};
+use crate::errors::Error;
+use crate::game::{Action, Game, set_anti_weights, set_phase_thresholds, set_weights, Weights};
+use crate::openings::{Openings, set_openings};
+use crate::rolit::{RolitBot, RolitGame};
+use crate::settings::EngineSettings;
+
+/// Runs the searches of the bot for the request handlers
+pub struct Engine {
+    settings: EngineSettings,
+    book: Option<Arc<Book>>,
+    permits: Semaphore,
+}
+
+impl Engine {
+
+    /// Creates a new engine, loading the book, the weights and the calibration from the given settings
+    pub fn new(settings: EngineSettings) -> Result<Self, Error> {
+        let book = match &settings.book_path {
+            Some(path) => {
+                let book = Book::load(path)?;
+                info!("Loaded {} boards from the opening book {}", book.len(), path.display());
+                Some(Arc::new(book))
+            },
+            None => None,
+        };
+
+        if let Some(path) = &settings.weights_path {
+            set_weights(Weights::load(path)?)?;
+        }
+
+        if let Some(path) = &settings.anti_weights_path {
+            set_anti_weights(Weights::load(path)?)?;
+        }
+
+        if let Some(phases) = settings.phases {
+            set_phase_thresholds(phases)?;
+        }
+
+        if let Some(path) = &settings.openings_path {
+            let openings = Openings::load(path)?;
+            info!("Loaded {} openings from {}", openings.len(), path.display());
+            set_openings(openings)?;
+        }
+
+        if let Some(path) = &settings.calibration_path {
+            set_calibration(Calibration::load(path)?)?;
+        }
+
+        if let Some(path) = &settings.ratings_path {
+            set_ratings(Ratings::load(path)?)?;
+        }
+
+        Ok(Self {
+            permits: Semaphore::new(settings.threads),
+            settings,
+            book,
+        })
+    }
+
+    /// Returns the intelligence to search with for the requested one
+    pub fn intelligence(&self, requested: Option<u32>) -> u32 {
+        min(requested.unwrap_or(self.settings.default_intelligence), self.settings.max_intelligence)
+    }
+
+    /// Returns the intelligence to search with for the requested one, or the one rated the closest to the requested
+    /// Elo rating when no intelligence is requested
+    pub fn intelligence_for(&self, requested: Option<u32>, elo: Option<f64>) -> u32 {
+        match (requested, elo) {
+            (None, Some(elo)) => ratings().intelligence_for(elo, self.settings.max_intelligence),
+            _ => self.intelligence(requested),
+        }
+    }
+
+    /// Returns the highest intelligence requests can ask for
+    pub fn max_intelligence(&self) -> u32 {
+        self.settings.max_intelligence
+    }
+
+    /// Returns the estimated rating of every intelligence requests can ask for
+    pub fn difficulties(&self) -> Vec<Difficulty> {
+        ratings().difficulties(self.settings.max_intelligence)
+    }
+
+    /// Creates a new bot with the intelligence to search with for the requested one
+    pub fn bot(&self, intelligence: Option<u32>) -> Bot {
+        let mut bot = Bot::new(self.intelligence(intelligence));
+        bot.set_cache_size(self.settings.cache_size);
+        if let Some(book) = &self.book {
+            bot.set_book(book.clone());
+        }
+        bot
+    }
+
+    /// Returns the time by which an analysis starting now must stop solving the end of the game exactly
+    fn solve_deadline(&self) -> Instant {
+        Instant::now() + Duration::from_millis(self.settings.solve_time)
+    }
+
+    /// Decides the next action of the bot from the given game, making the given mistakes, waiting for a free thread
+    pub async fn decide(&self, game: Game, intelligence: Option<u32>, mistakes: Option<Mistakes>)
+        -> Result<(Action, Game), Error> {
+        let mut bot = self.bot(intelligence);
+        if let Some(mistakes) = mistakes {
+            bot.set_mistakes(mistakes);
+        }
+
+        let _permit = self.permits.acquire().await
+            .expect("Semaphore of the engine is never closed");
+
+        task::spawn_blocking(move || bot.decide(&game))
+            .await
+            .expect("Search of the bot panicked")
+    }
+
+    /// Decides the next action like decide, reporting the progress of the search after each depth.
+    /// The search stops deepening once the time limit is spent, counted from when a thread is free.
+    pub async fn decide_with_progress<F>(&self, game: Game, intelligence: Option<u32>, mistakes: Option<Mistakes>,
+                                         time_limit: Option<Duration>, progress: F) -> Result<(Action, Game), Error>
+        where F: FnMut(u32, &Action) + Send + 'static {
+        let mut bot = self.bot(intelligence);
+        if let Some(mistakes) = mistakes {
+            bot.set_mistakes(mistakes);
+        }
+
+        let _permit = self.permits.acquire().await
+            .expect("Semaphore of the engine is never closed");
+        if let Some(time_limit) = time_limit {
+            bot.set_deadline(Instant::now() + time_limit);
+        }
+
+        task::spawn_blocking(move || bot.decide_iteratively(&game, progress))
+            .await
+            .expect("Search of the bot panicked")
+    }
+
+    /// Decides the placement of the player to move in the given Rolit game, waiting for a free thread
+    pub async fn decide_rolit(&self, game: RolitGame, intelligence: Option<u32>) -> Option<Position> {
+        let mut bot = RolitBot::new(self.intelligence(intelligence));
+
+        let _permit = self.permits.acquire().await
+            .expect("Semaphore of the engine is never closed");
+
+        task::spawn_blocking(move || bot.decide(&game))
+            .await
+            .expect("Search of the bot panicked")
+    }
+
+    /// Scores every legal move of the player to move in the given game, waiting for a free thread
+    pub async fn analyze(&self, game: Game, intelligence: Option<u32>) -> Vec<MoveAnalysis> {
+        let mut bot = self.bot(intelligence);
+
+        let _permit = self.permits.acquire().await
+            .expect("Semaphore of the engine is never closed");
+        let deadline = self.solve_deadline();
+
+        task::spawn_blocking(move || analyze(&mut bot, &game, Some(deadline)))
+            .await
+            .expect("Analysis of the bot panicked")
+    }
+
+    /// Reviews every move of the game played from the given start, waiting for a free thread
+    pub async fn review(&self, start: Game, moves: Vec<Position>, intelligence: Option<u32>)
+        -> Result<GameReview, Error> {
+        let mut bot = self.bot(intelligence);
+
+        let _permit = self.permits.acquire().await
+            .expect("Semaphore of the engine is never closed");
+        let deadline = self.solve_deadline();
+
+        task::spawn_blocking(move || review(&mut bot, &start, &moves, Some(deadline)))
+            .await
+            .expect("Review of the bot panicked")
+    }
+}
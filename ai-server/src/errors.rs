@@ -1,5 +1,21 @@
+use std::fmt::{Display, Formatter};
+
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     InvalidArgument(String),
-    ParseError(String)
+    ParseError(String),
+    InvalidConfig(String),
 }
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::InvalidArgument(msg) => write!(f, "Invalid argument: {}", msg),
+            Error::ParseError(msg) => write!(f, "Parse error: {}", msg),
+            Error::InvalidConfig(msg) => write!(f, "Configuration error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
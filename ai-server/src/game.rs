@@ -1,24 +1,45 @@
 use std::collections::HashSet;
 use std::fmt::{Display, Formatter};
+use std::fs;
 use std::hash::Hash;
-use lazy_static::lazy_static;
+use std::path::Path;
+use std::sync::OnceLock;
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
-use crate::board::{Board, Direction, Disk, Position};
+use crate::board::{Board, DEFAULT_BOARD_SIZE, Direction, Disk, MAX_BOARD_SIZE, Position};
 use crate::board::Disk::{Dark, Light};
 use crate::errors::Error;
-use crate::errors::Error::ParseError;
+use crate::errors::Error::{InvalidArgument, InvalidConfig, ParseError};
 use crate::game::Player::{Bot, Human};
 use crate::game::Phase::{Early, Mid, End};
 
 pub const BOT_CHAR: char = 'B';
 pub const HUMAN_CHAR: char = 'H';
 
-lazy_static! {
-    static ref MAX_BEST_EVALUATION: i32 = {
+/// Number of disks on the board at the start of a game
+const INITIAL_NUM_DISKS: usize = 4;
+
+/// Number of empty squares at the start of a game on a standard board, which the phase thresholds refer to
+const STANDARD_EMPTIES: usize = DEFAULT_BOARD_SIZE * DEFAULT_BOARD_SIZE - INITIAL_NUM_DISKS;
+
+/// Number of parts the weights are split into when tapering between the weights of two phases
+const TAPER_SCALE: i32 = 256;
+
+/// Highest magnitude of a weight, which keeps twice the evaluation of any game on the largest board within an i32
+pub const MAX_WEIGHT: i32 = 100;
+
+/// Best evaluations possible for max, by board size
+static MAX_BEST_EVALUATIONS: [OnceLock<i32>; MAX_BOARD_SIZE + 1] = [const { OnceLock::new() }; MAX_BOARD_SIZE + 1];
+
+/// Returns the best evaluation possible for max on a board of the given size
+pub fn max_best_evaluation(size: usize) -> i32 {
+    *MAX_BEST_EVALUATIONS[size].get_or_init(|| {
         assert_eq!(Bot.disk(), Light);
 
-        let mut board = Board::new();
-        for pos in Position::all() {
+        let mut board = Board::with_size(size).unwrap();
+        for pos in Position::all(size) {
             match board.disk(&pos) {
                 Some(Dark) => board.flip(&pos).unwrap(),
                 None => board.place(Bot.disk(), &pos).unwrap(),
@@ -26,19 +47,23 @@ lazy_static! {
             }
         }
 
-        let value = Game::parse(board, Player::default()).evaluate();
-        value
-    };
+        // The evaluation of a variant may favour the human on this board, so the larger bound of both is taken
+        Variant::ALL.into_iter()
+            .map(|variant| Game::parse(board.clone(), Player::default()).with_variant(variant).evaluate().abs())
+            .max()
+            .unwrap()
+    })
 }
 
-/// Returns the best evaluation possible for max
-pub fn max_best_evaluation() -> i32 {
-    *MAX_BEST_EVALUATION
+/// Returns the best evaluation possible for min on a board of the given size
+pub fn min_best_evaluation(size: usize) -> i32 {
+    -max_best_evaluation(size)
 }
 
-/// Returns the best evaluation possible for min
-pub fn min_best_evaluation() -> i32 {
-    -max_best_evaluation()
+/// Maps the given evaluation on a board of the given size into [0, 1], where 1 is the best evaluation for max
+pub fn normalize(evaluation: i32, size: usize) -> f32 {
+    let range = max_best_evaluation(size) - min_best_evaluation(size);
+    (evaluation - min_best_evaluation(size)) as f32 / range as f32
 }
 
 
@@ -46,11 +71,222 @@ pub fn min_best_evaluation() -> i32 {
 const PLACEMENT_WEIGHTS: [i32; 3] = [5, 4, 2];
 const MOBILITY_WEIGHTS: [i32; 3] = [5, 4, 3];
 const NUM_DISKS_WEIGHTS: [i32; 3] = [-1, -1, 0];
+const STABILITY_WEIGHTS: [i32; 3] = [10, 20, 30];
+const FRONTIER_WEIGHTS: [i32; 3] = [-3, -2, -1];
+const POTENTIAL_MOBILITY_WEIGHTS: [i32; 3] = [2, 2, 1];
+const PARITY_WEIGHTS: [i32; 3] = [0, 5, 20];
+const CORNERS_WEIGHTS: [i32; 3] = [30, 30, 20];
+const X_SQUARES_WEIGHTS: [i32; 3] = [-30, -20, -5];
+const C_SQUARES_WEIGHTS: [i32; 3] = [-10, -10, -5];
+
+/// Weights for early, mid and end stage of anti games, where the disks that are good to own in a standard game
+/// are the ones to give away, while keeping moves available still matters
+const ANTI_PLACEMENT_WEIGHTS: [i32; 3] = [-3, -3, -2];
+const ANTI_MOBILITY_WEIGHTS: [i32; 3] = [5, 5, 4];
+const ANTI_NUM_DISKS_WEIGHTS: [i32; 3] = [-1, -2, -4];
+const ANTI_STABILITY_WEIGHTS: [i32; 3] = [-10, -20, -30];
+const ANTI_FRONTIER_WEIGHTS: [i32; 3] = [2, 2, 1];
+const ANTI_POTENTIAL_MOBILITY_WEIGHTS: [i32; 3] = [2, 2, 1];
+const ANTI_PARITY_WEIGHTS: [i32; 3] = [0, -5, -20];
+const ANTI_CORNERS_WEIGHTS: [i32; 3] = [-30, -30, -20];
+const ANTI_X_SQUARES_WEIGHTS: [i32; 3] = [10, 10, 5];
+const ANTI_C_SQUARES_WEIGHTS: [i32; 3] = [5, 5, 2];
+
+static WEIGHTS: OnceLock<Weights> = OnceLock::new();
+static ANTI_WEIGHTS: OnceLock<Weights> = OnceLock::new();
+static PHASE_THRESHOLDS: OnceLock<PhaseThresholds> = OnceLock::new();
+
+/// Weights of each evaluation term for early, mid and end stage of the game
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Weights {
+    pub placement: [i32; 3],
+    pub mobility: [i32; 3],
+    pub num_disks: [i32; 3],
+    pub stability: [i32; 3],
+    pub frontier: [i32; 3],
+    pub potential_mobility: [i32; 3],
+    pub parity: [i32; 3],
+    pub corners: [i32; 3],
+    pub x_squares: [i32; 3],
+    pub c_squares: [i32; 3],
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            placement: PLACEMENT_WEIGHTS,
+            mobility: MOBILITY_WEIGHTS,
+            num_disks: NUM_DISKS_WEIGHTS,
+            stability: STABILITY_WEIGHTS,
+            frontier: FRONTIER_WEIGHTS,
+            potential_mobility: POTENTIAL_MOBILITY_WEIGHTS,
+            parity: PARITY_WEIGHTS,
+            corners: CORNERS_WEIGHTS,
+            x_squares: X_SQUARES_WEIGHTS,
+            c_squares: C_SQUARES_WEIGHTS,
+        }
+    }
+}
+
+impl Weights {
+
+    /// Returns the default weights for anti games
+    pub fn anti() -> Self {
+        Self {
+            placement: ANTI_PLACEMENT_WEIGHTS,
+            mobility: ANTI_MOBILITY_WEIGHTS,
+            num_disks: ANTI_NUM_DISKS_WEIGHTS,
+            stability: ANTI_STABILITY_WEIGHTS,
+            frontier: ANTI_FRONTIER_WEIGHTS,
+            potential_mobility: ANTI_POTENTIAL_MOBILITY_WEIGHTS,
+            parity: ANTI_PARITY_WEIGHTS,
+            corners: ANTI_CORNERS_WEIGHTS,
+            x_squares: ANTI_X_SQUARES_WEIGHTS,
+            c_squares: ANTI_C_SQUARES_WEIGHTS,
+        }
+    }
+
+    /// Loads the weights from the given JSON file
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let data = fs::read_to_string(path)
+            .map_err(|e| InvalidConfig(format!("Failed to read weights file {}: {}", path.display(), e)))?;
+
+        let weights: Self = serde_json::from_str(&data)
+            .map_err(|e| InvalidConfig(format!("Invalid weights file {}: {}", path.display(), e)))?;
+        weights.validate()?;
+        Ok(weights)
+    }
+
+    /// Checks that some weight counts, since the bounds of the evaluation would be 0 otherwise, and that no weight
+    /// is large enough for the evaluation to overflow
+    pub fn validate(&self) -> Result<(), Error> {
+        let weights = Term::ALL.into_iter().flat_map(|term| self.of(term)).collect_vec();
+
+        if weights.iter().all(|weight| *weight == 0) {
+            return Err(InvalidConfig("Evaluation weights must not all be 0".to_string()));
+        }
+        if let Some(weight) = weights.iter().find(|weight| weight.abs() > MAX_WEIGHT) {
+            return Err(InvalidConfig(format!("Evaluation weight {} must be within ±{}", weight, MAX_WEIGHT)));
+        }
+        Ok(())
+    }
+
+    /// Returns the weights of the given term
+    pub fn of(&self, term: Term) -> [i32; 3] {
+        match term {
+            Term::Placement => self.placement,
+            Term::Mobility => self.mobility,
+            Term::NumDisks => self.num_disks,
+            Term::Stability => self.stability,
+            Term::Frontier => self.frontier,
+            Term::PotentialMobility => self.potential_mobility,
+            Term::Parity => self.parity,
+            Term::Corners => self.corners,
+            Term::XSquares => self.x_squares,
+            Term::CSquares => self.c_squares,
+        }
+    }
+}
+
+/// Replaces the default evaluation weights with the given weights
+///
+/// Pre-conditions:
+/// * No game has been evaluated yet
+pub fn set_weights(weights: Weights) -> Result<(), Error> {
+    WEIGHTS.set(weights)
+        .map_err(|_| InvalidConfig("Evaluation weights are already in use".to_string()))
+}
+
+/// Replaces the default evaluation weights of anti games with the given weights
+///
+/// Pre-conditions:
+/// * No anti game has been evaluated yet
+pub fn set_anti_weights(weights: Weights) -> Result<(), Error> {
+    ANTI_WEIGHTS.set(weights)
+        .map_err(|_| InvalidConfig("Evaluation weights of anti games are already in use".to_string()))
+}
+
+/// Returns the evaluation weights in use for the given variant
+fn weights(variant: Variant) -> &'static Weights {
+    match variant {
+        Variant::Standard => WEIGHTS.get_or_init(Weights::default),
+        Variant::Anti => ANTI_WEIGHTS.get_or_init(Weights::anti),
+    }
+}
+
+/// Rules deciding who wins a game
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Variant {
+    /// The player with more disks wins
+    #[default]
+    Standard,
+    /// The player with fewer disks wins
+    Anti,
+}
+
+impl Variant {
+    pub const ALL: [Variant; 2] = [Variant::Standard, Variant::Anti];
+}
+
+/// Numbers of empty squares from which the middle and the end of the game start on a standard board
+///
+/// The numbers of empty squares of other boards are scaled to a standard board before being compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct PhaseThresholds {
+    pub mid: usize,
+    pub end: usize,
+}
+
+impl Default for PhaseThresholds {
+    fn default() -> Self {
+        Self {
+            mid: 40,
+            end: 20,
+        }
+    }
+}
+
+impl PhaseThresholds {
+
+    /// Checks if these thresholds split a game into three phases
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.end >= self.mid || self.mid >= STANDARD_EMPTIES {
+            return Err(InvalidConfig(format!(
+                "Phase thresholds must satisfy end ({}) < mid ({}) < {}", self.end, self.mid, STANDARD_EMPTIES)));
+        }
+        Ok(())
+    }
 
-#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+    /// Returns the numbers of empty squares at which the weights of each phase apply fully,
+    /// which are the middles of the phases
+    fn centers(&self) -> [usize; 3] {
+        [(STANDARD_EMPTIES + self.mid) / 2, (self.mid + self.end) / 2, self.end / 2]
+    }
+}
+
+/// Replaces the default phase thresholds with the given thresholds
+///
+/// Pre-conditions:
+/// * No game has been evaluated yet
+pub fn set_phase_thresholds(thresholds: PhaseThresholds) -> Result<(), Error> {
+    thresholds.validate()?;
+    PHASE_THRESHOLDS.set(thresholds)
+        .map_err(|_| InvalidConfig("Phase thresholds are already in use".to_string()))
+}
+
+/// Returns the phase thresholds in use
+fn phase_thresholds() -> &'static PhaseThresholds {
+    PHASE_THRESHOLDS.get_or_init(PhaseThresholds::default)
+}
+
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 pub enum Player {
     #[default]
+    #[serde(rename = "B")]
     Bot,
+    #[serde(rename = "H")]
     Human,
 }
 
@@ -80,6 +316,14 @@ impl Player {
             Human => Dark,
         } 
     }
+
+    /// Returns the player of the given disk
+    pub fn of(disk: Disk) -> Self {
+        match disk {
+            Dark => Human,
+            Light => Bot,
+        }
+    }
 }
 
 impl Display for Player {
@@ -92,7 +336,41 @@ impl Display for Player {
 }
 
 
-#[derive(Default, PartialEq, Hash, Eq)]
+/// Traditional handicap, where the weaker side is given corners in the order a1, h8, h1 and a8
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Handicap {
+    /// Side receiving the handicap
+    pub side: Player,
+    /// Number of corners given, from 1 to 4
+    pub corners: usize,
+    /// Whether the corners are blocked, so that the stronger side can never take them,
+    /// instead of holding disks of the weaker side
+    #[serde(default)]
+    pub blocked: bool,
+}
+
+impl Handicap {
+
+    /// Gives the corners of this handicap on the given board
+    pub fn apply(&self, board: &mut Board) -> Result<(), Error> {
+        if !(1..=4).contains(&self.corners) {
+            return Err(InvalidArgument(format!("A handicap gives 1 to 4 corners: {}", self.corners)));
+        }
+
+        let last = board.size() - 1;
+        let corners = [Position::new(0, 0), Position::new(last, last), Position::new(0, last), Position::new(last, 0)];
+        for corner in &corners[..self.corners] {
+            if self.blocked {
+                board.block(corner)?;
+            } else {
+                board.place(self.side.disk(), corner)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Hash, Eq, Serialize, Deserialize, JsonSchema)]
 pub struct Action {
     player: Player,
     placement: Position,
@@ -106,6 +384,16 @@ impl Action {
             placement
         }
     }
+
+    /// Returns the player taking this action
+    pub fn player(&self) -> Player {
+        self.player
+    }
+
+    /// Returns the position where this action places a disk
+    pub fn placement(&self) -> &Position {
+        &self.placement
+    }
 }
 
 impl Display for Action {
@@ -114,8 +402,123 @@ impl Display for Action {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash, Default)]
-enum Phase {
+/// Number of disks of each colour on a board
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct DiskCount {
+    pub dark: usize,
+    pub light: usize,
+}
+
+impl DiskCount {
+
+    /// Counts the disks on the given board
+    pub fn new(board: &Board) -> Self {
+        Self {
+            dark: board.positions(Dark).count(),
+            light: board.positions(Light).count(),
+        }
+    }
+}
+
+/// Disks flipped by an action in one direction from its placement
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct FlippedLine {
+    pub direction: Direction,
+    /// Flipped positions, ordered by the distance from the placement
+    pub positions: Vec<Position>,
+}
+
+/// Changes made to the board by an action
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct MoveOutcome {
+    pub placement: Position,
+    pub flipped: Vec<FlippedLine>,
+    pub disks_before: DiskCount,
+    pub disks_after: DiskCount,
+}
+
+impl MoveOutcome {
+
+    /// Returns all flipped positions regardless of their direction
+    pub fn flipped_positions(&self) -> impl Iterator<Item=&Position> {
+        self.flipped.iter().flat_map(|line| line.positions.iter())
+    }
+}
+
+/// Term of the evaluation, compared between the players
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Term {
+    /// Sum of the weights of the squares taken
+    Placement,
+    /// Number of actions available
+    Mobility,
+    /// Number of disks
+    NumDisks,
+    /// Number of disks that can never be flipped
+    Stability,
+    /// Number of disks next to an empty square
+    Frontier,
+    /// Number of empty squares next to a disk of the opponent
+    PotentialMobility,
+    /// Number of regions with an odd number of empty squares, counted for the player to move
+    /// who can take the last square of each
+    Parity,
+    /// Number of corners taken
+    Corners,
+    /// Number of squares taken diagonally next to an empty corner
+    XSquares,
+    /// Number of squares taken on an edge next to an empty corner
+    CSquares,
+}
+
+impl Term {
+    pub const ALL: [Term; 10] = [
+        Term::Placement, Term::Mobility, Term::NumDisks, Term::Stability, Term::Frontier, Term::PotentialMobility,
+        Term::Parity, Term::Corners, Term::XSquares, Term::CSquares,
+    ];
+}
+
+/// Value of one term in the evaluation of a game
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TermBreakdown {
+    pub term: Term,
+    /// Raw values of the term for each player
+    pub bot: i32,
+    pub human: i32,
+    /// Weight of the term, tapered between the weights of the phases around the number of empty squares
+    pub weight: f32,
+    /// Weight times the raw value of the bot minus the raw value of the human
+    pub contribution: f32,
+}
+
+/// Part of the placement term added by the disk on one square
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SquareContribution {
+    pub position: Position,
+    pub player: Player,
+    /// Weight of the square
+    pub weight: i32,
+    /// Weight of the square times the placement weight, negative for the disks of the human
+    pub contribution: f32,
+}
+
+/// How the evaluation of a game adds up, where positive values favour the bot
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct EvaluationBreakdown {
+    pub phase: Phase,
+    pub empties: usize,
+    pub terms: Vec<TermBreakdown>,
+    /// Contributions of the occupied squares, row by row
+    pub squares: Vec<SquareContribution>,
+    /// Evaluation, which is the sum of the contributions of the terms rounded down
+    pub total: i32,
+}
+
+/// Stage of the game
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
     #[default]
     Early,
     Mid,
@@ -124,20 +527,44 @@ enum Phase {
 
 impl Phase {
 
-    /// Creates a new stage of the game depending of the current turn
-    pub fn new(turn: usize) -> Self {
-        if turn < 20 {
+    /// Creates a new stage of the game depending on the number of empty squares
+    pub fn new(empties: usize) -> Self {
+        let thresholds = phase_thresholds();
+
+        if empties > thresholds.mid {
             Early
-        } else if turn < 40 {
+        } else if empties > thresholds.end {
             Mid
         } else {
             End
         }
     }
 
+    /// Returns the parts of TAPER_SCALE that the weights of each phase get at the given number of empty squares,
+    /// moving linearly from the weights of one phase to the next between their middles
+    fn shares(empties: usize) -> [i32; 3] {
+        let centers = phase_thresholds().centers();
+        let mut shares = [0; 3];
+
+        if empties >= centers[0] {
+            shares[0] = TAPER_SCALE;
+        } else if empties <= centers[2] {
+            shares[2] = TAPER_SCALE;
+        } else {
+            let from = if empties >= centers[1] { 0 } else { 1 };
+            let span = (centers[from] - centers[from + 1]) as i32;
+            let moved = (centers[from] - empties) as i32 * TAPER_SCALE / span;
+
+            shares[from] = TAPER_SCALE - moved;
+            shares[from + 1] = moved;
+        }
+
+        shares
+    }
+
     /// Convert this state into index for getting weights
-    pub fn to_index(&self) -> usize {
-        match *self {
+    pub fn to_index(self) -> usize {
+        match self {
             Early => 0,
             Mid => 1,
             _ => 2,
@@ -145,11 +572,16 @@ impl Phase {
     }
 }
 
+/// Returns the weights of each phase mixed by the given shares, in parts of TAPER_SCALE
+fn taper(weights: [i32; 3], shares: [i32; 3]) -> i32 {
+    weights.iter().zip(shares).map(|(weight, share)| weight * share).sum()
+}
+
 #[derive(Clone, PartialEq, Eq, Hash, Default)]
 pub struct Game {
     board: Board,
     current_player: Player,
-    phase: Phase,
+    variant: Variant,
     winner: Option<Player>,
 }
 
@@ -160,20 +592,17 @@ impl Game {
         Self {
             board: Board::new(),
             current_player: Bot,
-            phase: Phase::new(0),
+            variant: Variant::Standard,
             winner: None,
         }
     }
     
     /// Parses the given data into a Game
     pub fn parse(board: Board, current_player: Player) -> Self {
-        const INITIAL_NUM_DISKS: usize = 4;
-        let turn = board.positions(Dark).count() + board.positions(Light).count() - INITIAL_NUM_DISKS;
-
         let mut game = Self {
             board,
             current_player,
-            phase: Phase::new(turn),
+            variant: Variant::Standard,
             winner: None,
         };
         
@@ -183,6 +612,33 @@ impl Game {
         
         game
     }
+
+    /// Returns this game played with the rules of the given variant
+    pub fn with_variant(mut self, variant: Variant) -> Self {
+        self.variant = variant;
+        if self.is_over() {
+            self.set_winner();
+        }
+
+        self
+    }
+
+    /// Returns this game with the colours of every disk and of the player to move swapped
+    pub fn swap_colours(&self) -> Self {
+        let mut board = self.board.clone();
+        for pos in Position::all(board.size()) {
+            if board.disk(&pos).is_some() {
+                board.flip(&pos).unwrap();
+            }
+        }
+
+        Game::parse(board, self.current_player.opponent()).with_variant(self.variant)
+    }
+
+    /// Returns the rules this game is played with
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
     
     /// Returns the current player of this turn
     pub fn current_player(&self) -> Player {
@@ -203,7 +659,7 @@ impl Game {
                     let disk =  self.board.disk(walker.as_ref().unwrap());
                     
                     if disk.is_none() {
-                        if distance > 1 {
+                        if distance > 1 && !self.board.is_blocked(walker.as_ref().unwrap()) {
                             actions.insert(Action { player, placement: walker.unwrap() });
                         }
                         break;
@@ -225,7 +681,14 @@ impl Game {
     
     /// Returns the new state with the action applied
     pub fn result(&self, action: &Action) -> Self {
+        self.result_with_outcome(action).0
+    }
+
+    /// Returns the new state with the action applied, along with the changes the action made to the board
+    pub fn result_with_outcome(&self, action: &Action) -> (Self, MoveOutcome) {
         let mut game = self.clone();
+        let disks_before = DiskCount::new(&self.board);
+        let mut flipped = Vec::new();
 
         game.board.place(action.player.disk(), &action.placement).unwrap();
         
@@ -248,10 +711,11 @@ impl Game {
                 walker = neighbour.unwrap();
             }
             
-            if game.board.disk(&walker) == Some(action.player.disk()) {
-                for pos in path {
-                    game.board.flip(&pos).unwrap();
+            if game.board.disk(&walker) == Some(action.player.disk()) && !path.is_empty() {
+                for pos in path.iter() {
+                    game.board.flip(pos).unwrap();
                 }
+                flipped.push(FlippedLine { direction: dir, positions: path });
             }
         }
 
@@ -259,6 +723,25 @@ impl Game {
         if game.is_over() {
             game.set_winner();
         }
+
+        let outcome = MoveOutcome {
+            placement: action.placement.clone(),
+            flipped,
+            disks_before,
+            disks_after: DiskCount::new(&game.board),
+        };
+        (game, outcome)
+    }
+    
+    /// Returns the new state where the current player passes the turn to the opponent
+    ///
+    /// Pre-conditions:
+    /// * The current player has no actions
+    pub fn pass(&self) -> Self {
+        assert!(self.actions(self.current_player).next().is_none());
+
+        let mut game = self.clone();
+        game.current_player = self.current_player.opponent();
         game
     }
     
@@ -268,10 +751,15 @@ impl Game {
         let num_bot_disks = self.board.positions(Bot.disk()).count();
         let num_human_disks = self.board.positions(Human.disk()).count();
 
+        let (more, fewer) = match self.variant {
+            Variant::Standard => (Bot, Human),
+            Variant::Anti => (Human, Bot),
+        };
+
         self.winner = if num_bot_disks > num_human_disks {
-            Some(Bot)
+            Some(more)
         } else if num_human_disks > num_bot_disks {
-            Some(Human)
+            Some(fewer)
         } else {
             None
         };
@@ -279,7 +767,7 @@ impl Game {
     
     /// Checks if this game is over
     pub fn is_over(&self) -> bool {
-        self.actions(Bot).next() == None && self.actions(Human).next() == None
+        self.actions(Bot).next().is_none() && self.actions(Human).next().is_none()
     }
     
     /// Returns the winner of the game
@@ -295,6 +783,19 @@ impl Game {
     pub fn board(&self) -> &Board {
         &self.board
     }
+
+    /// Returns the stage of the game
+    pub fn phase(&self) -> Phase {
+        Phase::new(self.standard_empties())
+    }
+
+    /// Returns the number of empty squares scaled to a standard board
+    fn standard_empties(&self) -> usize {
+        let squares = self.board.size() * self.board.size() - self.board.blocked_positions().count();
+        // Boards without any square left to fill at the start count as fully filled
+        let fillable = squares.saturating_sub(INITIAL_NUM_DISKS).max(1);
+        self.board.empty_positions().count() * STANDARD_EMPTIES / fillable
+    }
     
     /// Returns the utility of this game
     /// 
@@ -304,34 +805,101 @@ impl Game {
         assert!(self.is_over());
         
         match self.winner {
-            Some(Bot) => max_best_evaluation(),
-            Some(_) => min_best_evaluation(),
+            Some(Bot) => max_best_evaluation(self.board.size()),
+            Some(_) => min_best_evaluation(self.board.size()),
             None => 0,
         }
     }
     
     /// Evaluates this game state to a value
     pub fn evaluate(&self) -> i32 {
-        let phase_index = self.phase.to_index();
-
-        PLACEMENT_WEIGHTS[phase_index] * (
-            self.board.positions(Bot.disk())
-                .map(|p| p.weight())
-                .sum::<i32>() -
-            self.board.positions(Human.disk())
-                .map(|p| p.weight())
-                .sum::<i32>()
-        ) + MOBILITY_WEIGHTS[phase_index] * (
-            self.actions(Bot)
-                .count() as i32 -
-            self.actions(Human)
-                .count() as i32
-        ) + NUM_DISKS_WEIGHTS[phase_index] * (
-            self.board.positions(Bot.disk())
-                .count() as i32 -
-            self.board.positions(Human.disk())
-                .count() as i32
-        )
+        self.evaluate_with(weights(self.variant))
+    }
+
+    /// Evaluates this game state to a value with the given weights instead of the ones in use
+    pub fn evaluate_with(&self, weights: &Weights) -> i32 {
+        let shares = Phase::shares(self.standard_empties());
+
+        Term::ALL.into_iter()
+            .map(|term| (term, taper(weights.of(term), shares)))
+            // Some terms are expensive, so they are skipped when they don't count
+            .filter(|(_, weight)| *weight != 0)
+            .map(|(term, weight)| weight * (self.term(term, Bot) - self.term(term, Human)))
+            .sum::<i32>()
+            .div_euclid(TAPER_SCALE)
+    }
+
+    /// Returns the raw value of the given evaluation term for the given player
+    fn term(&self, term: Term, player: Player) -> i32 {
+        match term {
+            Term::Placement => self.board.positions(player.disk())
+                .map(|p| p.weight(self.board.size()))
+                .sum(),
+            Term::Mobility => self.actions(player).count() as i32,
+            Term::NumDisks => self.board.positions(player.disk()).count() as i32,
+            Term::Stability => self.board.stable_positions(player.disk()).len() as i32,
+            Term::Frontier => self.board.frontier_positions(player.disk()).count() as i32,
+            Term::PotentialMobility => self.board.potential_mobility(player.disk()) as i32,
+            Term::Parity if player == self.current_player => self.board.empty_regions().into_iter()
+                .filter(|region| region.len() % 2 == 1)
+                .count() as i32,
+            Term::Parity => 0,
+            Term::Corners => self.board.positions(player.disk())
+                .filter(|p| p.is_corner(self.board.size()))
+                .count() as i32,
+            Term::XSquares => self.next_to_empty_corner(player, Position::is_x_square),
+            Term::CSquares => self.next_to_empty_corner(player, Position::is_c_square),
+        }
+    }
+
+    /// Returns the number of squares of the given kind taken by the player next to an empty corner
+    fn next_to_empty_corner(&self, player: Player, is_kind: fn(&Position, usize) -> bool) -> i32 {
+        let size = self.board.size();
+        self.board.positions(player.disk())
+            .filter(|p| is_kind(p, size))
+            .filter(|p| p.adjacent_corner(size).is_some_and(|corner| self.board.disk(&corner).is_none()))
+            .count() as i32
+    }
+
+    /// Returns how each term and each square adds up to the evaluation of this game
+    pub fn breakdown(&self) -> EvaluationBreakdown {
+        let empties = self.board.empty_positions().count();
+        let shares = Phase::shares(self.standard_empties());
+        let weights = weights(self.variant);
+
+        let terms = Term::ALL.into_iter()
+            .map(|term| {
+                let bot = self.term(term, Bot);
+                let human = self.term(term, Human);
+                let weight = taper(weights.of(term), shares) as f32 / TAPER_SCALE as f32;
+
+                TermBreakdown { term, bot, human, weight, contribution: weight * (bot - human) as f32 }
+            })
+            .collect_vec();
+        let placement_weight = taper(weights.placement, shares) as f32 / TAPER_SCALE as f32;
+
+        let size = self.board.size();
+        let squares = Position::all(size)
+            .filter_map(|position| {
+                let player = [Bot, Human].into_iter().find(|p| self.board.disk(&position) == Some(p.disk()))?;
+                let sign = if player == Bot { 1.0 } else { -1.0 };
+
+                Some(SquareContribution {
+                    weight: position.weight(size),
+                    contribution: sign * placement_weight * position.weight(size) as f32,
+                    position,
+                    player,
+                })
+            })
+            .collect();
+
+        EvaluationBreakdown {
+            phase: self.phase(),
+            empties,
+            total: self.evaluate(),
+            terms,
+            squares,
+        }
     }
 }
 
@@ -339,9 +907,11 @@ impl Game {
 mod tests {
     use itertools::Itertools;
 
-    use crate::board::{Board, BOARD_SIZE};
+    use crate::board::{Board, DEFAULT_BOARD_SIZE as BOARD_SIZE, MAX_BOARD_SIZE};
+    use crate::board::Direction::{East, South};
     use crate::board::Position;
-    use crate::game::{Action, Game};
+    use crate::game::{Action, DiskCount, FlippedLine, Game, Handicap, max_best_evaluation, MAX_WEIGHT, Phase,
+                      PhaseThresholds, taper, TAPER_SCALE, Term, Variant, Weights};
     use crate::game::Player::{Bot, Human};
 
     #[test]
@@ -403,4 +973,207 @@ mod tests {
             assert_eq!(game.board.disk(&Position::new(i, i)), Some(Bot.disk()))
         }
     }
+
+    #[test]
+    fn result_with_outcome() {
+        let mut game = Game::new();
+        game.board.clear();
+
+        // Flips two disks to the east and one to the south, but nothing to the south east
+        game.board.place(Human.disk(), &Position::new(0, 1)).unwrap();
+        game.board.place(Human.disk(), &Position::new(0, 2)).unwrap();
+        game.board.place(Bot.disk(), &Position::new(0, 3)).unwrap();
+        game.board.place(Human.disk(), &Position::new(1, 0)).unwrap();
+        game.board.place(Bot.disk(), &Position::new(2, 0)).unwrap();
+        game.board.place(Human.disk(), &Position::new(1, 1)).unwrap();
+
+        let (game, outcome) = game.result_with_outcome(&Action{player: Bot, placement: Position::new(0, 0)});
+
+        assert_eq!(outcome.placement, Position::new(0, 0));
+        assert_eq!(outcome.flipped, vec![
+            FlippedLine { direction: East, positions: vec![Position::new(0, 1), Position::new(0, 2)] },
+            FlippedLine { direction: South, positions: vec![Position::new(1, 0)] },
+        ]);
+        assert_eq!(outcome.flipped_positions().count(), 3);
+        assert_eq!(outcome.disks_before, DiskCount { dark: 4, light: 2 });
+        assert_eq!(outcome.disks_after, DiskCount { dark: 1, light: 6 });
+        assert_eq!(game.board.disk(&Position::new(1, 1)), Some(Human.disk()));
+    }
+
+    #[test]
+    fn breakdown() {
+        let mut board = Board::new();
+        board.place(Human.disk(), &Position::new(0, 0)).unwrap();
+        let game = Game::parse(board, Bot);
+
+        let breakdown = game.breakdown();
+        assert_eq!(breakdown.phase, Phase::Early);
+        assert_eq!(breakdown.total, game.evaluate());
+        assert_eq!(breakdown.terms.iter().map(|term| term.term).collect_vec(), Term::ALL.to_vec());
+
+        assert_eq!(breakdown.empties, 59);
+        assert!((breakdown.terms.iter().map(|term| term.contribution).sum::<f32>() - breakdown.total as f32).abs() < 1.0);
+
+        let num_disks = &breakdown.terms[2];
+        assert_eq!((num_disks.bot, num_disks.human), (2, 3));
+        assert_eq!(num_disks.contribution, -num_disks.weight);
+
+        assert_eq!(breakdown.squares.len(), 5);
+        let corner = &breakdown.squares[0];
+        assert_eq!(corner.position, Position::new(0, 0));
+        assert_eq!(corner.player, Human);
+        assert!(corner.contribution < 0.0);
+        assert_eq!(breakdown.squares.iter().map(|square| square.contribution).sum::<f32>(),
+                   breakdown.terms[0].contribution);
+    }
+
+    #[test]
+    fn terms() {
+        let mut board = Board::new();
+        board.clear();
+        board.place(Bot.disk(), &Position::new(0, 0)).unwrap();
+        board.place(Bot.disk(), &Position::new(0, 1)).unwrap();
+        board.place(Human.disk(), &Position::new(6, 6)).unwrap();
+        board.place(Human.disk(), &Position::new(7, 6)).unwrap();
+        board.place(Human.disk(), &Position::new(3, 3)).unwrap();
+        let game = Game::parse(board, Human);
+
+        assert_eq!((game.term(Term::Stability, Bot), game.term(Term::Stability, Human)), (2, 0));
+        assert_eq!((game.term(Term::Corners, Bot), game.term(Term::Corners, Human)), (1, 0));
+        assert_eq!((game.term(Term::XSquares, Bot), game.term(Term::XSquares, Human)), (0, 1));
+        assert_eq!((game.term(Term::CSquares, Bot), game.term(Term::CSquares, Human)), (0, 1));
+        assert_eq!(game.term(Term::Frontier, Human), 3);
+        assert_eq!(game.term(Term::Parity, Bot), 0);
+        assert_eq!(game.term(Term::Parity, Human), 1);
+    }
+
+    #[test]
+    fn board_sizes() {
+        let game = Game::parse(Board::with_size(4).unwrap(), Human);
+        assert_eq!(game.actions(Human).count(), 4);
+        assert_eq!(game.phase(), Phase::Early);
+        assert!(game.evaluate().abs() < max_best_evaluation(4));
+
+        // Half of the squares of a larger board are empty in the middle of the game like on a standard board
+        let mut board = Board::with_size(12).unwrap();
+        for pos in board.empty_positions().take(70).collect::<Vec<_>>() {
+            board.place(Human.disk(), &pos).unwrap();
+        }
+        assert_eq!(Game::parse(board, Human).phase(), Phase::Mid);
+        assert!(max_best_evaluation(12) > max_best_evaluation(8));
+    }
+
+    #[test]
+    fn holes() {
+        // Lines through a hole neither give a move nor flip
+        let board = Board::parse("EEEE\nXLDE\nEDLE\nXEEE".to_string()).unwrap();
+        let game = Game::parse(board, Human);
+        let actions = game.actions(Human).map(|action| action.placement().to_string()).sorted().collect_vec();
+        assert_eq!(actions, vec!["0,1", "2,3", "3,2"]);
+
+        let result = game.result(&Action::parse(Human, Position::new(2, 3)));
+        assert_eq!(result.board().to_string(), "EEEE\nXLDE\nEDDD\nXEEE");
+
+        // Boards with every empty square blocked, or fewer squares than the start, still evaluate
+        for board in ["XXXX\nXLDX\nXDLX\nXXXX", "XXXX\nXLDX\nXDXX\nXXXX"] {
+            let game = Game::parse(Board::parse(board.to_string()).unwrap(), Human);
+            game.evaluate();
+            assert_eq!(game.phase(), Phase::End);
+        }
+    }
+
+    #[test]
+    fn anti() {
+        // The bot ends with more disks
+        let board = Board::parse("LLLL\nLLLL\nLDDD\nDDDD".to_string()).unwrap();
+        let game = Game::parse(board.clone(), Human);
+        assert_eq!(game.winner(), Some(Bot));
+        assert!(game.utility() > 0);
+
+        let game = game.with_variant(Variant::Anti);
+        assert_eq!(game.winner(), Some(Human));
+        assert!(game.utility() < 0);
+        let game = Game::new().with_variant(Variant::Anti);
+        let action = game.actions(Bot).next().unwrap();
+        assert_eq!(game.result(&action).variant(), Variant::Anti);
+
+        // Corners are worth having in a standard game and worth avoiding in an anti game
+        let mut board = Board::new();
+        board.place(Bot.disk(), &Position::new(0, 0)).unwrap();
+        board.place(Human.disk(), &Position::new(0, BOARD_SIZE - 1)).unwrap();
+        board.place(Bot.disk(), &Position::new(BOARD_SIZE - 1, 0)).unwrap();
+        assert!(Game::parse(board.clone(), Human).evaluate() > 0);
+        assert!(Game::parse(board, Human).with_variant(Variant::Anti).evaluate() < 0);
+    }
+
+    #[test]
+    fn swap_colours() {
+        let game = Game::parse(Board::new(), Human).result(&Action::parse(Human, Position::new(2, 4)));
+        let swapped = game.swap_colours();
+        assert_eq!(swapped.current_player(), Human);
+        assert_eq!(swapped.board().positions(Bot.disk()).count(), game.board().positions(Human.disk()).count());
+        assert_eq!(swapped.evaluate(), -game.evaluate());
+        assert!(swapped.swap_colours() == game);
+
+        assert_eq!(game.evaluate_with(&Weights::default()), game.evaluate());
+        assert_ne!(game.evaluate_with(&Weights::anti()), game.evaluate());
+    }
+
+    #[test]
+    fn weights() {
+        assert!(Weights::default().validate().is_ok());
+        assert!(Weights::anti().validate().is_ok());
+
+        let zero = Weights { placement: [0; 3], mobility: [0; 3], num_disks: [0; 3], stability: [0; 3],
+            frontier: [0; 3], potential_mobility: [0; 3], parity: [0; 3], corners: [0; 3], x_squares: [0; 3],
+            c_squares: [0; 3] };
+        assert!(zero.validate().is_err());
+        assert!(Weights { stability: [0, MAX_WEIGHT + 1, 0], ..Weights::default() }.validate().is_err());
+
+        // The largest weights don't overflow on the largest board, even doubled as the spread of the bounds
+        let largest = Weights { placement: [MAX_WEIGHT; 3], mobility: [MAX_WEIGHT; 3], num_disks: [MAX_WEIGHT; 3],
+            stability: [MAX_WEIGHT; 3], frontier: [MAX_WEIGHT; 3], potential_mobility: [MAX_WEIGHT; 3],
+            parity: [MAX_WEIGHT; 3], corners: [MAX_WEIGHT; 3], x_squares: [MAX_WEIGHT; 3], c_squares: [MAX_WEIGHT; 3] };
+        assert!(largest.validate().is_ok());
+        let board = Board::parse(vec!["L".repeat(MAX_BOARD_SIZE); MAX_BOARD_SIZE].join("\n")).unwrap();
+        let evaluation = Game::parse(board, Human).evaluate_with(&largest);
+        assert!(evaluation > 0);
+        assert!(evaluation.checked_mul(2).is_some());
+    }
+
+    #[test]
+    fn handicap() {
+        let mut board = Board::new();
+        Handicap { side: Human, corners: 3, blocked: false }.apply(&mut board).unwrap();
+        assert_eq!(board.positions(Human.disk()).count(), 5);
+        assert_eq!(board.disk(&Position::new(0, BOARD_SIZE - 1)), Some(Human.disk()));
+        assert_eq!(board.disk(&Position::new(BOARD_SIZE - 1, 0)), None);
+
+        let mut board = Board::with_size(6).unwrap();
+        Handicap { side: Bot, corners: 4, blocked: true }.apply(&mut board).unwrap();
+        assert_eq!(board.blocked_positions().count(), 4);
+        assert!(board.is_blocked(&Position::new(5, 5)));
+
+        assert!(Handicap { side: Bot, corners: 0, blocked: false }.apply(&mut Board::new()).is_err());
+    }
+
+    #[test]
+    fn phases() {
+        assert_eq!(Phase::new(60), Phase::Early);
+        assert_eq!(Phase::new(41), Phase::Early);
+        assert_eq!(Phase::new(40), Phase::Mid);
+        assert_eq!(Phase::new(20), Phase::End);
+
+        // The weights move from one phase to the next between their middles at 50, 30 and 10 empty squares
+        assert_eq!(Phase::shares(55), [TAPER_SCALE, 0, 0]);
+        assert_eq!(Phase::shares(40), [TAPER_SCALE / 2, TAPER_SCALE / 2, 0]);
+        assert_eq!(Phase::shares(30), [0, TAPER_SCALE, 0]);
+        assert_eq!(Phase::shares(25), [0, TAPER_SCALE * 3 / 4, TAPER_SCALE / 4]);
+        assert_eq!(Phase::shares(0), [0, 0, TAPER_SCALE]);
+        assert_eq!(taper([4, 2, 0], Phase::shares(40)), 3 * TAPER_SCALE);
+
+        assert!(PhaseThresholds { mid: 20, end: 20 }.validate().is_err());
+        assert!(PhaseThresholds { mid: 60, end: 20 }.validate().is_err());
+        assert!(PhaseThresholds { mid: 30, end: 10 }.validate().is_ok());
+    }
 }
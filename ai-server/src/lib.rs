@@ -0,0 +1,19 @@
+#[macro_use] extern crate rocket;
+
+pub mod analysis;
+pub mod api;
+pub mod board;
+pub mod book;
+pub mod bot;
+pub mod calibration;
+pub mod clock;
+pub mod cors;
+pub mod difficulty;
+pub mod engine;
+pub mod errors;
+pub mod game;
+pub mod openings;
+pub mod rolit;
+pub mod session;
+pub mod settings;
+pub mod tournament;
@@ -1,70 +1,108 @@
 #[macro_use] extern crate rocket;
 
-use std::collections::HashSet;
+use std::{env, fs, thread};
 
-use game::{max_best_evaluation, min_best_evaluation};
 use itertools::Itertools;
-use rocket::fairing::{Fairing, Info, Kind};
-
-use rocket::http::Header;
-use rocket::{Request, Response};
 use rocket::response::status::BadRequest;
-use serde_json::{json, Value};
+use rocket::serde::json::Json;
+use rocket::{Build, Rocket, State};
+use rocket_okapi::settings::OpenApiSettings;
+use rocket_okapi::{mount_endpoints_and_merged_docs, openapi, openapi_get_routes_spec};
+use schemars::JsonSchema;
+use serde::Serialize;
 
-use crate::board::{Board, Position};
-use crate::bot::Bot;
-use crate::game::{Action, Game, Player};
+use desdemona::{api, calibration, difficulty, session};
+use desdemona::analysis::MoveAnalysis;
+use desdemona::board::{Board, DEFAULT_BOARD_SIZE, EMPTY_CHAR, Position};
+use desdemona::calibration::Calibration;
+use desdemona::cors::Cors;
+use desdemona::engine::Engine;
+use desdemona::game::{Action, Game, MoveOutcome, normalize, Player};
+use desdemona::session::Sessions;
+use desdemona::settings::Settings;
 
-mod board;
-mod errors;
-mod game;
-mod bot;
 
+/// Board of a game after a move
+#[derive(Serialize, JsonSchema)]
+struct MoveResult {
+    board: String,
+    /// Positions of the disks flipped by the move, given when a disk was placed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    flipped: Option<Vec<String>>,
+    /// Winner of the game, given once it is over and null for a draw
+    #[serde(skip_serializing_if = "Option::is_none")]
+    winner: Option<Option<String>>,
+}
 
-fn serialize_result(game: &Game) -> Value {
-    let mut json = json!({
-        "board": game.board().to_string(),
-    });
+/// Next action of the bot with the board it results in
+#[derive(Serialize, JsonSchema)]
+struct Decision {
+    /// Position of the action, which is null when the bot has no actions
+    decision: Option<String>,
+    result: MoveResult,
+}
 
-    if game.is_over() {
-        json["winner"] = serde_json::to_value(game.winner().map(|p| p.to_string()))
-            .unwrap_or_else(|_| Value::Null);
+fn serialize_result(game: &Game, outcome: Option<&MoveOutcome>) -> MoveResult {
+    MoveResult {
+        board: game.board().to_string(),
+        flipped: outcome.map(|outcome| outcome.flipped_positions().map(|pos| pos.to_string()).collect_vec()),
+        winner: game.is_over().then(|| game.winner().map(|p| p.to_string())),
     }
-    
-    json
 }
 
 
+/// Parses a board given to the first version of the API, which takes partial boards and fills the cells they are
+/// missing with empty squares of a standard board
+fn parse_board(board: String) -> Result<Board, BadRequest<String>> {
+    Board::parse(board.clone())
+        .or_else(|e| {
+            let rows = board.lines().collect_vec();
+            if rows.len() > DEFAULT_BOARD_SIZE || rows.iter().any(|row| row.chars().count() > DEFAULT_BOARD_SIZE) {
+                return Err(e);
+            }
+
+            let padded = (0..DEFAULT_BOARD_SIZE)
+                .map(|i| {
+                    let row = rows.get(i).copied().unwrap_or_default();
+                    let missing = DEFAULT_BOARD_SIZE - row.chars().count();
+                    format!("{}{}", row, EMPTY_CHAR.to_string().repeat(missing))
+                })
+                .join("\n");
+            Board::parse(padded)
+        })
+        .map_err(|_| BadRequest(Some("Invalid board".to_string())))
+}
+
+#[openapi(tag = "v1")]
 #[get("/")]
 fn index() -> &'static str {
     "Hello World!"
 }
 
-#[get("/initial-board")]
-fn initial_board() -> String {
-    Board::new().to_string()
+/// Returns the board of the given size at the start of a game, which is 8 by default
+#[openapi(tag = "v1")]
+#[get("/initial-board?<size>")]
+fn initial_board(size: Option<usize>) -> Result<String, BadRequest<String>> {
+    Board::with_size(size.unwrap_or(DEFAULT_BOARD_SIZE))
+        .map(|board| board.to_string())
+        .map_err(|_| BadRequest(Some("Invalid size".to_string())))
 }
 
+/// Evaluates the board for the bot, where 1 is the best and 0 is the worst
+#[openapi(tag = "v1")]
 #[get("/evaluate?<board>")]
-fn evaluate(board: String) -> Result<String, BadRequest<String>> {
-    let board = Board::parse(board);
-    if board.is_err() {
-        return Err(BadRequest(Some("Invalid board".to_string())));
-    }
+fn evaluate(board: String) -> Result<Json<f32>, BadRequest<String>> {
+    let board = parse_board(board)?;
 
-    let evaluation = Game::parse(board.unwrap(), Player::default()).evaluate();
-
-    let range = max_best_evaluation() - min_best_evaluation();
-    let normalized = (evaluation - min_best_evaluation()) as f32 / range as f32;
-    Ok(normalized.to_string())
+    let game = Game::parse(board, Player::default());
+    Ok(Json(normalize(game.evaluate(), game.board().size())))
 }
 
+/// Places a disk of the player at the position and returns the resulting board
+#[openapi(tag = "v1")]
 #[get("/result?<board>&<position>&<player>")]
-fn result(board: String, position: String, player: String) -> Result<String, BadRequest<String>> {
-    let board = Board::parse(board);
-    if board.is_err() {
-        return Err(BadRequest(Some("Invalid board".to_string())));
-    }
+fn result(board: String, position: String, player: String) -> Result<Json<MoveResult>, BadRequest<String>> {
+    let board = parse_board(board)?;
     
     let player = player.chars().next();
     if player.is_none() {
@@ -77,24 +115,23 @@ fn result(board: String, position: String, player: String) -> Result<String, Bad
     }
     let player = player.unwrap();
 
-    let game = Game::parse(board.unwrap(), player.clone());
+    let game = Game::parse(board, player);
     let action = Action::parse(player, Position::parse(position).unwrap());
     
     if !game.actions(player).contains(&action) {
         return Err(BadRequest(Some("Invalid action for the given player".to_string())));
     }
 
-    let game = game.result(&action);
+    let (game, outcome) = game.result_with_outcome(&action);
     
-    Ok(serialize_result(&game).to_string())
+    Ok(Json(serialize_result(&game, Some(&outcome))))
 }
 
+/// Returns the positions the player can place a disk at
+#[openapi(tag = "v1")]
 #[get("/actions?<board>&<player>")]
-fn actions(board: String, player: String) -> Result<String, BadRequest<String>> {
-    let board = Board::parse(board);
-    if board.is_err() {
-        return Err(BadRequest(Some("Invalid board".to_string())));
-    }
+fn actions(board: String, player: String) -> Result<Json<Vec<String>>, BadRequest<String>> {
+    let board = parse_board(board)?;
 
     let player = player.chars().next();
     if player.is_none() {
@@ -107,95 +144,220 @@ fn actions(board: String, player: String) -> Result<String, BadRequest<String>>
     }
     let player = player.unwrap();
     
-    let game = Game::parse(board.unwrap(), player);
-    Ok(Value::Array(
-        game.actions(player)
-            .map(|a| Value::String(a.to_string()))
-            .collect_vec()
-    ).to_string())
+    let game = Game::parse(board, player);
+    Ok(Json(game.actions(player).map(|a| a.to_string()).collect_vec()))
 }
 
+/// Decides the next action of the bot
+#[openapi(tag = "v1")]
 #[get("/decide?<board>&<intelligence>")]
-fn decide(board: String, intelligence: u32) -> Result<String, BadRequest<String>> {
-    let mut bot = Bot::new(intelligence);
-    let board = Board::parse(board);
-    if board.is_err() {
-        return Err(BadRequest(Some("Invalid board".to_string())));
-    }
+async fn decide(board: String, intelligence: Option<u32>, engine: &State<Engine>)
+    -> Result<Json<Decision>, BadRequest<String>> {
+    let board = parse_board(board)?;
     
-    let game = Game::parse(board.unwrap(), Player::Bot);
+    let game = Game::parse(board, Player::Bot);
     
-    let decision = bot.decide(&game);
+    let decision = engine.decide(game.clone(), intelligence, None).await;
     
     if decision.is_err() { // No available actions
-        let json = json!({
-            "decision": Value::Null,
-            "result": serialize_result(&game),
-        });
-        return Ok(json.to_string());
+        return Ok(Json(Decision { decision: None, result: serialize_result(&game, None) }));
     }
     
-    let (action, game) = decision.unwrap();
+    let (action, _) = decision.unwrap();
+    let (game, outcome) = game.result_with_outcome(&action);
     
-    let json = json!({
-        "decision": action.to_string(),
-        "result": serialize_result(&game),
-    });
-    
-    Ok(json.to_string())
+    Ok(Json(Decision { decision: Some(action.to_string()), result: serialize_result(&game, Some(&outcome)) }))
 }
 
-#[rocket::main]
-async fn main() -> Result<(), rocket::Error> {
-    let allowed_origins: HashSet<String> = [
-        "http://localhost:443",
-        "https://localhost",
-        "https://localhost:80",  
-        "http://localhost:8080",
-        "http://localhost",
-        "http://desdemona.seoulsky.org",
-        "http://desdemona.seoulsky.org:443",
-        "https://desdemona.seoulsky.org",
-        ].iter()
-        .map(|s| s.to_string())
-        .collect();
-
-    rocket::build()
-        .mount("/api", routes![index, initial_board, evaluate, result, actions, decide])
-        .attach(Cors::new(allowed_origins))
-        .launch()
-        .await?;
+/// Scores every legal move of the player, best first
+#[openapi(tag = "v1")]
+#[get("/analyze?<board>&<player>&<intelligence>")]
+async fn analyze(board: String, player: String, intelligence: Option<u32>, engine: &State<Engine>)
+    -> Result<Json<Vec<MoveAnalysis>>, BadRequest<String>> {
+    let board = parse_board(board)?;
+
+    let player = player.chars().next();
+    if player.is_none() {
+        return Err(BadRequest(Some("Invalid player".to_string())));
+    }
+
+    let player = Player::parse(player.unwrap());
+    if player.is_err() {
+        return Err(BadRequest(Some("Invalid player".to_string())));
+    }
+
+    let game = Game::parse(board, player.unwrap());
+    let analyses = engine.analyze(game, intelligence).await;
+
+    Ok(Json(analyses))
+}
+
+/// Mounts the routes of every version of the API along with their OpenAPI specification at /api/openapi.json
+fn mount_api(mut rocket: Rocket<Build>) -> Rocket<Build> {
+    let openapi_settings = OpenApiSettings::default();
+
+    mount_endpoints_and_merged_docs! {
+        rocket, "/api", openapi_settings,
+        "" => openapi_get_routes_spec![openapi_settings: index, initial_board, evaluate, result, actions, decide,
+                                                    analyze],
+        "/v2" => api::routes(&openapi_settings),
+        "/v2/games" => session::routes(&openapi_settings),
+    };
 
+    rocket.register(api::BASE, api::catchers())
+}
+
+/// Fits the win probability models on self-play games and writes them to a JSON file
+///
+/// Usage: `desdemona calibrate <games> <intelligence> <file>`
+fn calibrate(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let [num_games, intelligence, path] = args else {
+        return Err("Usage: desdemona calibrate <games> <intelligence> <file>".into());
+    };
+
+    let samples = calibration::self_play(num_games.parse()?, intelligence.parse()?, rand::random());
+    let calibration = Calibration::fit(&samples);
+
+    fs::write(path, serde_json::to_string_pretty(&calibration)?)?;
+    println!("Fitted {} positions into {}", samples.len(), path);
     Ok(())
 }
 
-pub struct Cors {
-    allowed_origins: HashSet<String>,
+/// Rates every intelligence in Elo against the one below it and writes the ratings to a JSON file
+///
+/// Usage: `desdemona rate <pairs> <intelligence> <file>`
+fn rate(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let [pairs, max_intelligence, path] = args else {
+        return Err("Usage: desdemona rate <pairs> <intelligence> <file>".into());
+    };
+
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let ratings = difficulty::rate(pairs.parse()?, max_intelligence.parse()?, threads, rand::random())?;
+
+    fs::write(path, serde_json::to_string_pretty(&ratings)?)?;
+    println!("Rated {} intelligences into {}: {:?}", ratings.elo.len(), path, ratings.elo);
+    Ok(())
 }
 
-impl Cors {
-    pub fn new(allowed_origins: HashSet<String>) -> Cors {
-        Cors { allowed_origins }
+#[rocket::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = env::args().collect_vec();
+    match args.get(1).map(String::as_str) {
+        Some("calibrate") => return calibrate(&args[2..]),
+        Some("rate") => return rate(&args[2..]),
+        _ => {},
     }
+
+    let rocket = rocket::build();
+
+    let settings = Settings::load(rocket.figment())?;
+    let cors = Cors::new(&settings.cors)?;
+    let engine = Engine::new(settings.engine.clone())?;
+    let sessions = Sessions::new(settings.sessions.clone());
+
+    mount_api(rocket)
+        .attach(cors)
+        .manage(settings)
+        .manage(engine)
+        .manage(sessions)
+        .launch()
+        .await?;
+
+    Ok(())
 }
 
-#[rocket::async_trait]
-impl Fairing for Cors {
-    fn info(&self) -> Info {
-        Info {
-            name: "CORS Fairing",
-            kind: Kind::Response,
+#[cfg(test)]
+mod tests {
+    use rocket::http::Status;
+    use rocket::local::blocking::Client;
+    use serde_json::{json, Value};
+
+    use desdemona::board::{Board, DEFAULT_BOARD_SIZE, Disk, Position};
+    use desdemona::engine::Engine;
+    use desdemona::session::Sessions;
+    use desdemona::settings::{EngineSettings, SessionSettings};
+
+    use crate::{mount_api, parse_board};
+
+    #[test]
+    fn openapi() {
+        let rocket = mount_api(rocket::build())
+            .manage(Engine::new(EngineSettings::default()).unwrap())
+            .manage(Sessions::new(SessionSettings::default()));
+        let client = Client::tracked(rocket).unwrap();
+
+        let response = client.get("/api/openapi.json").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let spec = response.into_json::<Value>().unwrap();
+        assert_eq!(spec["servers"][0]["url"], "/api");
+        for path in ["/decide", "/actions", "/result", "/evaluate",
+                     "/v2/decide", "/v2/actions", "/v2/result", "/v2/evaluate",
+                     "/v2/games/", "/v2/games/{id}/events", "/v2/games/{id}/join", "/v2/games/{id}/moves"] {
+            assert!(spec["paths"][path].is_object(), "{} is missing", path);
         }
+
+        let responses = &spec["paths"]["/v2/result"]["post"]["responses"];
+        assert!(responses["200"].is_object());
+        assert!(responses["400"].is_object());
+        assert!(spec["components"]["schemas"]["ErrorResponse"].is_object());
+        assert!(spec["components"]["schemas"]["Board"].is_object());
+
+        // The routes of the first version describe their JSON bodies rather than plain strings
+        let content = &spec["paths"]["/decide"]["get"]["responses"]["200"]["content"];
+        assert!(content["application/json"].is_object());
+        assert!(spec["components"]["schemas"]["Decision"].is_object());
+        assert!(spec["components"]["schemas"]["MoveAnalysis"].is_object());
     }
 
-    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
-        let origin = request.headers().get_one("Origin").unwrap_or("");
+    #[test]
+    fn v1_bodies() {
+        let rocket = mount_api(rocket::build())
+            .manage(Engine::new(EngineSettings::default()).unwrap())
+            .manage(Sessions::new(SessionSettings::default()));
+        let client = Client::tracked(rocket).unwrap();
+        let board = Board::new().to_string().replace('\n', "%0A");
 
-        if self.allowed_origins.contains(origin) {
-            response.set_header(Header::new("Access-Control-Allow-Origin", origin));
-            response.set_header(Header::new("Access-Control-Allow-Methods", "GET"));
-            response.set_header(Header::new("Access-Control-Allow-Headers", "*"));
-            response.set_header(Header::new("Access-Control-Allow-Credentials", "true"));
-        }
+        let response = client.get(format!("/api/result?board={}&position=2,4&player=H", board)).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let result = response.into_json::<Value>().unwrap();
+        assert_eq!(result["flipped"], json!(["3,4"]));
+        assert!(result.get("winner").is_none());
+
+        let response = client.get(format!("/api/actions?board={}&player=H", board)).dispatch();
+        assert_eq!(response.into_json::<Vec<String>>().unwrap().len(), 4);
+
+        let response = client.get(format!("/api/evaluate?board={}", board)).dispatch();
+        assert!((0.0..=1.0).contains(&response.into_json::<f32>().unwrap()));
+
+        let response = client.get(format!("/api/decide?board={}&intelligence=1", board)).dispatch();
+        let decision = response.into_json::<Value>().unwrap();
+        assert!(decision["decision"].is_string());
+        assert!(decision["result"]["board"].is_string());
+
+        let response = client.get("/api/evaluate?board=Z").dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn v1_partial_boards() {
+        // Partial boards are padded with empty squares to a standard board, as the first version always did
+        let board = parse_board("EEE\nEEEDL\nEEELD".to_string()).unwrap();
+        assert_eq!(board.size(), DEFAULT_BOARD_SIZE);
+        assert_eq!(board.disk(&Position::new(1, 3)), Some(Disk::Dark));
+        assert_eq!(board.disk(&Position::new(2, 3)), Some(Disk::Light));
+        assert_eq!(board.disk(&Position::new(7, 7)), None);
+
+        assert_eq!(parse_board("EEEEEE\n".repeat(6)).unwrap().size(), 6);
+        assert!(parse_board("EEEEEEEEE".to_string()).is_err());
+        assert!(parse_board("EEZ".to_string()).is_err());
+
+        let rocket = mount_api(rocket::build())
+            .manage(Engine::new(EngineSettings::default()).unwrap())
+            .manage(Sessions::new(SessionSettings::default()));
+        let client = Client::tracked(rocket).unwrap();
+        let response = client.get("/api/actions?board=EEE%0AEEEDL%0AEEELD%0A&player=H").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert!(!response.into_json::<Vec<String>>().unwrap().is_empty());
     }
 }
@@ -0,0 +1,256 @@
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use rand::rngs::StdRng;
+use itertools::Itertools;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rocket::FromFormField;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::board::{Board, DEFAULT_BOARD_SIZE, Layout, Position};
+use crate::calibration::calibration;
+use crate::errors::Error;
+use crate::errors::Error::{InvalidArgument, InvalidConfig, ParseError};
+use crate::game::{Action, Game, Player};
+
+/// Number of moves of the openings generated when no list is loaded
+const XOT_MOVES: usize = 8;
+
+/// Highest difference between the chances of winning and losing of a generated opening
+const MAX_IMBALANCE: f32 = 0.1;
+
+/// Number of random openings tried before settling for the most balanced one
+const MAX_ATTEMPTS: usize = 100;
+
+static OPENINGS: OnceLock<Openings> = OnceLock::new();
+
+/// Position a game starts from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, FromFormField)]
+#[serde(rename_all = "snake_case")]
+pub enum Start {
+    /// Disks of the same colour on the diagonals, as in a standard game
+    #[default]
+    #[field(value = "diagonal")]
+    Diagonal,
+    /// Disks of the same colour side by side
+    #[field(value = "straight")]
+    Straight,
+    /// Balanced opening already played on a standard board, chosen by the seed
+    #[field(value = "xot")]
+    Xot,
+}
+
+impl Start {
+
+    /// Returns the board of the given size at this start, choosing the opening from the seed
+    pub fn board(self, size: usize, seed: u64) -> Result<Board, Error> {
+        match self {
+            Start::Diagonal => Board::with_layout(size, Layout::Diagonal),
+            Start::Straight => Board::with_layout(size, Layout::Straight),
+            Start::Xot if size != DEFAULT_BOARD_SIZE =>
+                Err(InvalidArgument(format!("Openings are played on boards of size {}", DEFAULT_BOARD_SIZE))),
+            Start::Xot => Ok(match openings() {
+                Some(openings) => openings.choose(seed),
+                None => generate(seed),
+            }),
+        }
+    }
+}
+
+/// List of balanced openings, each played from the standard start with dark moving first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Openings {
+    boards: Vec<Board>,
+}
+
+impl Openings {
+
+    /// Parses the given data into openings
+    ///
+    /// Each non-empty line holds the moves of an opening in algebraic notation, such as "f5d6c3d3c4f4f6f3".
+    /// Openings have an even number of moves, so that dark is to move after them like at the start.
+    /// They are written for the usual start where black moves first from d5 and e4, and are mirrored onto
+    /// this board, where dark starts on d4 and e5.
+    pub fn parse(data: &str) -> Result<Self, Error> {
+        let mut boards = Vec::new();
+
+        for (i, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let moves = split_moves(line)
+                .map_err(|e| InvalidConfig(format!("Invalid opening at line {}: {}", i + 1, e)))?;
+            if moves.len() % 2 != 0 {
+                return Err(InvalidConfig(format!("Opening at line {} has an odd number of moves", i + 1)));
+            }
+
+            let board = play(&moves)
+                .map_err(|e| InvalidConfig(format!("Invalid opening at line {}: {}", i + 1, e)))?;
+            boards.push(board);
+        }
+
+        if boards.is_empty() {
+            return Err(InvalidConfig("The list of openings is empty".to_string()));
+        }
+        Ok(Self { boards })
+    }
+
+    /// Loads the openings from the given file
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let data = fs::read_to_string(path)
+            .map_err(|e| InvalidConfig(format!("Failed to read openings file {}: {}", path.display(), e)))?;
+
+        Self::parse(&data)
+    }
+
+    /// Returns the board of the opening chosen by the seed
+    pub fn choose(&self, seed: u64) -> Board {
+        let mut rng = StdRng::seed_from_u64(seed);
+        self.boards.choose(&mut rng).unwrap().clone()
+    }
+
+    /// Returns the number of openings in this list
+    pub fn len(&self) -> usize {
+        self.boards.len()
+    }
+
+    /// Checks if this list has no openings
+    pub fn is_empty(&self) -> bool {
+        self.boards.is_empty()
+    }
+}
+
+/// Replaces the generated openings with the given list
+///
+/// Pre-conditions:
+/// * No opening has been chosen yet
+pub fn set_openings(openings: Openings) -> Result<(), Error> {
+    OPENINGS.set(openings)
+        .map_err(|_| InvalidConfig("Openings are already in use".to_string()))
+}
+
+/// Returns the list of openings in use, if one was loaded
+fn openings() -> Option<&'static Openings> {
+    OPENINGS.get()
+}
+
+/// Splits the given moves written one after another in algebraic notation
+fn split_moves(line: &str) -> Result<Vec<Position>, Error> {
+    let mut moves = Vec::new();
+    let mut rest = line.trim();
+
+    while !rest.is_empty() {
+        let end = rest.char_indices()
+            .skip(1)
+            .find(|(_, ch)| ch.is_ascii_alphabetic())
+            .map_or(rest.len(), |(i, _)| i);
+        moves.push(Position::parse_algebraic(rest[..end].trim())?);
+        rest = rest[end..].trim_start();
+    }
+
+    Ok(moves)
+}
+
+/// Returns the board after playing the given moves from the standard start with dark moving first,
+/// mirroring their columns
+fn play(moves: &[Position]) -> Result<Board, Error> {
+    let mut game = Game::parse(Board::new(), Player::Human);
+
+    for position in moves {
+        if !position.is_inbound(DEFAULT_BOARD_SIZE) {
+            return Err(ParseError(format!("Move is off the board: {}", position.to_algebraic())));
+        }
+        let player = game.current_player();
+        let mirrored = Position::new(position.row(), DEFAULT_BOARD_SIZE - 1 - position.col());
+        let action = Action::parse(player, mirrored);
+        if !game.actions(player).any(|act| act == action) {
+            return Err(InvalidArgument(format!("Illegal move {}", position.to_algebraic())));
+        }
+        game = game.result(&action);
+    }
+
+    Ok(game.board().clone())
+}
+
+/// Plays random openings chosen by the seed until one is balanced according to the calibrated predictions,
+/// or returns the most balanced one tried
+fn generate(seed: u64) -> Board {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut best = (f32::INFINITY, Board::new());
+
+    for _ in 0..MAX_ATTEMPTS {
+        let mut game = Game::parse(Board::new(), Player::Human);
+        for _ in 0..XOT_MOVES {
+            let player = game.current_player();
+            // Actions come in no particular order, so they are sorted for the seed to give the same opening
+            let actions = game.actions(player).sorted_by_key(|action| action.placement().clone()).collect_vec();
+            let Some(action) = actions.choose(&mut rng) else {
+                break;
+            };
+            game = game.result(action);
+        }
+
+        // Dark must be to move again, which fails only when a player had to pass
+        if game.current_player() != Player::Human || game.is_over() {
+            continue;
+        }
+
+        let prediction = calibration().predict(&game);
+        let imbalance = (prediction.win - prediction.loss).abs();
+        if imbalance <= MAX_IMBALANCE {
+            return game.board().clone();
+        }
+        if imbalance < best.0 {
+            best = (imbalance, game.board().clone());
+        }
+    }
+
+    best.1
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use crate::board::{Board, Position};
+    use crate::game::{Game, Player};
+    use crate::openings::{generate, Openings, Start, XOT_MOVES};
+
+    #[test]
+    fn parse() {
+        let openings = Openings::parse("\
+        # Openings\n\
+        f5d6c3d3c4f4f6f3\n\
+        \n\
+        e6 f4 e3 f6\n").unwrap();
+        assert_eq!(openings.len(), 2);
+
+        let empties = (0..20).map(|seed| openings.choose(seed).empty_positions().count()).sorted().dedup().collect_vec();
+        assert_eq!(empties, vec![52, 56]);
+
+        assert!(Openings::parse("f5d6c3").is_err());
+        assert!(Openings::parse("a1a2").is_err());
+        assert!(Openings::parse("i1a1").is_err());
+        assert!(Openings::parse("f5z").is_err());
+        assert!(Openings::parse("").is_err());
+    }
+
+    #[test]
+    fn starts() {
+        let board = Start::Straight.board(6, 0).unwrap();
+        assert_eq!(board.disk(&Position::new(2, 3)), board.disk(&Position::new(2, 2)));
+
+        let board = Start::Xot.board(8, 3).unwrap();
+        assert_eq!(board, generate(3));
+        assert_eq!(board.empty_positions().count(), 64 - 4 - XOT_MOVES);
+        assert!(Game::parse(board, Player::Human).actions(Player::Human).next().is_some());
+        assert_ne!(generate(3), Board::new());
+
+        assert!(Start::Xot.board(6, 0).is_err());
+    }
+}
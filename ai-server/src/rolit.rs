@@ -0,0 +1,455 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use itertools::Itertools;
+use schemars::gen::SchemaGenerator;
+use schemars::JsonSchema;
+use schemars::schema::Schema;
+use serde::{Deserialize, Serialize};
+
+use crate::board::{check_size, DEFAULT_BOARD_SIZE, Direction, EMPTY_CHAR, format_grid, MAX_BOARD_SIZE,
+                   MIN_BOARD_SIZE, parse_grid, Position};
+use crate::errors::Error;
+use crate::errors::Error::{InvalidArgument, ParseError};
+
+pub const MIN_PLAYERS: usize = 2;
+pub const MAX_PLAYERS: usize = 4;
+
+/// Value of every ball on top of the weight of its square
+const BALL_VALUE: i32 = 10;
+
+/// Value of each ball of difference at the end of a game, above any evaluation
+const UTILITY_SCALE: i32 = 10_000;
+
+/// Colour of a ball in Rolit, listed in the order the players take turns
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Serialize, Deserialize, JsonSchema)]
+pub enum Colour {
+    #[serde(rename = "R")]
+    Red,
+    #[serde(rename = "G")]
+    Green,
+    #[serde(rename = "Y")]
+    Yellow,
+    #[serde(rename = "B")]
+    Blue,
+}
+
+impl Display for Colour {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
+}
+
+impl Colour {
+
+    /// Returns all colours in turn order
+    pub fn all() -> [Colour; MAX_PLAYERS] {
+        [Colour::Red, Colour::Green, Colour::Yellow, Colour::Blue]
+    }
+
+    /// Parses the given character into a colour
+    pub fn parse(ch: char) -> Result<Self, Error> {
+        Colour::all().into_iter()
+            .find(|colour| colour.to_char() == ch)
+            .ok_or_else(|| ParseError(format!("Invalid character to parse into a colour: {}", ch)))
+    }
+
+    fn to_char(self) -> char {
+        match self {
+            Colour::Red => 'R',
+            Colour::Green => 'G',
+            Colour::Yellow => 'Y',
+            Colour::Blue => 'B',
+        }
+    }
+
+    fn to_index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Square board of Rolit, where every ball takes one of four colours.
+///
+/// Rolit keeps its own board and game rather than generalizing the two-colour ones, whose evaluation, book, handicaps
+/// and variants only make sense for two players. Both share the grid format and the line traversal of the board
+/// module, but Rolit boards have no blocked squares.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "Vec<String>", into = "Vec<String>")]
+pub struct RolitBoard {
+    size: usize,
+    grid: [[Option<Colour>; MAX_BOARD_SIZE]; MAX_BOARD_SIZE],
+}
+
+impl Default for RolitBoard {
+    fn default() -> Self {
+        RolitBoard::new()
+    }
+}
+
+impl Display for RolitBoard {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let rows = format_grid(self.size, |pos| self.ball(pos).map_or(EMPTY_CHAR, Colour::to_char));
+        write!(f, "{}", rows)
+    }
+}
+
+impl TryFrom<Vec<String>> for RolitBoard {
+    type Error = Error;
+
+    fn try_from(rows: Vec<String>) -> Result<Self, Self::Error> {
+        RolitBoard::parse(&rows.join("\n"))
+    }
+}
+
+impl From<RolitBoard> for Vec<String> {
+    fn from(board: RolitBoard) -> Self {
+        board.to_string().lines().map(|line| line.to_string()).collect()
+    }
+}
+
+impl JsonSchema for RolitBoard {
+    fn schema_name() -> String {
+        "RolitBoard".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let mut schema = gen.subschema_for::<Vec<String>>().into_object();
+        schema.metadata().description = Some(format!(
+            "Rows of a square board of an even size, where each cell is 'R', 'G', 'Y' or 'B' for a ball of that \
+            colour or '{}' for empty", EMPTY_CHAR));
+        schema.array().min_items = Some(MIN_BOARD_SIZE as u32);
+        schema.array().max_items = Some(MAX_BOARD_SIZE as u32);
+        schema.into()
+    }
+}
+
+impl RolitBoard {
+
+    /// Creates a new board of the standard size
+    pub fn new() -> Self {
+        Self::initial(DEFAULT_BOARD_SIZE)
+    }
+
+    /// Creates a new board of the given size
+    pub fn with_size(size: usize) -> Result<Self, Error> {
+        check_size(size)?;
+        Ok(Self::initial(size))
+    }
+
+    /// Creates the board at the start of a game of the given valid size, with a ball of every colour in the centre
+    /// going clockwise from red at the upper left
+    fn initial(size: usize) -> Self {
+        let mut board = RolitBoard {
+            size,
+            grid: [[None; MAX_BOARD_SIZE]; MAX_BOARD_SIZE],
+        };
+
+        let mid = size / 2 - 1;
+        board.grid[mid][mid] = Some(Colour::Red);
+        board.grid[mid][mid + 1] = Some(Colour::Yellow);
+        board.grid[mid + 1][mid + 1] = Some(Colour::Blue);
+        board.grid[mid + 1][mid] = Some(Colour::Green);
+
+        board
+    }
+
+    /// Parses the given data to a board, whose size is the number of rows
+    pub fn parse(data: &str) -> Result<Self, Error> {
+        let rows = parse_grid(data)?;
+        let mut board = RolitBoard::initial(rows.len());
+
+        for (i, row) in rows.into_iter().enumerate() {
+            for (j, ch) in row.into_iter().enumerate() {
+                board.grid[i][j] = if ch == EMPTY_CHAR { None } else { Some(Colour::parse(ch)?) };
+            }
+        }
+        Ok(board)
+    }
+
+    /// Returns the number of rows and columns of this board
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the ball at the given position
+    pub fn ball(&self, pos: &Position) -> Option<Colour> {
+        self.grid[pos.row()][pos.col()]
+    }
+
+    /// Returns all positions without a ball
+    pub fn empty_positions(&self) -> impl Iterator<Item=Position> + '_ {
+        Position::all(self.size).filter(|pos| self.ball(pos).is_none())
+    }
+
+    /// Returns the number of balls of each colour, in turn order
+    pub fn counts(&self) -> [usize; MAX_PLAYERS] {
+        let mut counts = [0; MAX_PLAYERS];
+        for colour in Position::all(self.size).filter_map(|pos| self.ball(&pos)) {
+            counts[colour.to_index()] += 1;
+        }
+        counts
+    }
+
+    /// Checks if a ball is next to the given position in any direction
+    fn touches_ball(&self, pos: &Position) -> bool {
+        Direction::all().any(|dir| pos.neighbour(dir, self.size).is_some_and(|neighbour| self.ball(&neighbour).is_some()))
+    }
+
+    /// Returns the lines of balls of other colours a ball of the given colour at the given position captures
+    fn captures(&self, colour: Colour, pos: &Position) -> Vec<Vec<Position>> {
+        Direction::all()
+            .filter_map(|dir| {
+                let mut path = Vec::new();
+                for current in pos.ray(dir, self.size) {
+                    match self.ball(&current) {
+                        Some(ball) if ball == colour => return if path.is_empty() { None } else { Some(path) },
+                        Some(_) => path.push(current),
+                        None => return None,
+                    }
+                }
+                None
+            })
+            .collect()
+    }
+}
+
+/// Game of Rolit between two to four players, who play red, green, yellow and blue in this order.
+/// Balls of colours nobody plays stay on the board and can be captured.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RolitGame {
+    board: RolitBoard,
+    num_players: usize,
+    current: Colour,
+}
+
+impl RolitGame {
+
+    /// Creates a new game on the given board for the given number of players, where the given colour is to move
+    pub fn new(board: RolitBoard, num_players: usize, current: Colour) -> Result<Self, Error> {
+        if !(MIN_PLAYERS..=MAX_PLAYERS).contains(&num_players) {
+            return Err(InvalidArgument(format!(
+                "Number of players must be between {} and {}: {}", MIN_PLAYERS, MAX_PLAYERS, num_players)));
+        }
+        if current.to_index() >= num_players {
+            return Err(InvalidArgument(format!("Nobody plays {} in a game of {} players", current, num_players)));
+        }
+
+        Ok(Self { board, num_players, current })
+    }
+
+    pub fn board(&self) -> &RolitBoard {
+        &self.board
+    }
+
+    /// Returns the colour of the player to move
+    pub fn current(&self) -> Colour {
+        self.current
+    }
+
+    /// Returns the colours of the players in turn order
+    pub fn players(&self) -> impl Iterator<Item=Colour> {
+        Colour::all().into_iter().take(self.num_players)
+    }
+
+    /// Returns the positions the current player can place a ball at, in row order.
+    /// A ball must be placed next to another ball, and must capture when any placement of the player captures.
+    pub fn actions(&self) -> Vec<Position> {
+        let touching = self.board.empty_positions()
+            .filter(|pos| self.board.touches_ball(pos))
+            .collect_vec();
+        let capturing = touching.iter()
+            .filter(|pos| !self.board.captures(self.current, pos).is_empty())
+            .cloned()
+            .collect_vec();
+
+        if capturing.is_empty() { touching } else { capturing }
+    }
+
+    /// Returns the new state with a ball of the current player placed at the given position
+    pub fn result(&self, placement: &Position) -> Result<Self, Error> {
+        if !self.actions().contains(placement) {
+            return Err(InvalidArgument(format!("{} can't place a ball at {}", self.current, placement)));
+        }
+
+        Ok(self.apply(placement))
+    }
+
+    /// Returns the new state with a ball of the current player placed at the given position, without checking that
+    /// the placement is one of the actions
+    fn apply(&self, placement: &Position) -> Self {
+        let mut game = self.clone();
+        for pos in self.board.captures(self.current, placement).into_iter().flatten() {
+            game.board.grid[pos.row()][pos.col()] = Some(self.current);
+        }
+        game.board.grid[placement.row()][placement.col()] = Some(self.current);
+        game.current = Colour::all()[(self.current.to_index() + 1) % self.num_players];
+
+        game
+    }
+
+    /// Checks if the board is full
+    pub fn is_over(&self) -> bool {
+        self.board.empty_positions().next().is_none()
+    }
+
+    /// Returns the players with the most balls, who share the win
+    pub fn winners(&self) -> Vec<Colour> {
+        let counts = self.board.counts();
+        let most = self.players().map(|colour| counts[colour.to_index()]).max().unwrap_or(0);
+        self.players().filter(|colour| counts[colour.to_index()] == most).collect()
+    }
+
+    /// Returns the value of the game for the given player, assuming the others play against it
+    fn evaluate(&self, player: Colour) -> i32 {
+        let size = self.board.size;
+        let mut scores = [0; MAX_PLAYERS];
+        for pos in Position::all(size) {
+            if let Some(colour) = self.board.ball(&pos) {
+                scores[colour.to_index()] += BALL_VALUE + pos.weight(size);
+            }
+        }
+
+        if self.is_over() {
+            scores = self.board.counts().map(|count| count as i32 * UTILITY_SCALE);
+        }
+        let best_opponent = self.players()
+            .filter(|colour| *colour != player)
+            .map(|colour| scores[colour.to_index()])
+            .max()
+            .unwrap_or(0);
+        scores[player.to_index()] - best_opponent
+    }
+}
+
+/// Bot of Rolit, which searches with the paranoid assumption that every other player plays against it
+pub struct RolitBot {
+    depth_limit: u32,
+    pub num_nodes_expanded: u32,
+}
+
+impl RolitBot {
+
+    /// Creates a new instance of the bot
+    pub fn new(intelligence: u32) -> Self {
+        Self {
+            depth_limit: intelligence,
+            num_nodes_expanded: 0,
+        }
+    }
+
+    /// Decides the placement of the current player, which is None when the game is over
+    pub fn decide(&mut self, game: &RolitGame) -> Option<Position> {
+        let player = game.current();
+        let mut best: Option<(i32, Position)> = None;
+
+        for placement in game.actions() {
+            let result = game.apply(&placement);
+            let alpha = best.as_ref().map_or(i32::MIN, |(value, _)| *value);
+            let value = self.paranoid(&result, player, alpha, i32::MAX, 1);
+            if best.as_ref().is_none_or(|(best_value, _)| value > *best_value) {
+                best = Some((value, placement));
+            }
+        }
+
+        best.map(|(_, placement)| placement)
+    }
+
+    /// Returns the value of the given game for the player, who maximizes it while the others minimize it
+    fn paranoid(&mut self, game: &RolitGame, player: Colour, mut alpha: i32, mut beta: i32, depth: u32) -> i32 {
+        if game.is_over() || depth > self.depth_limit {
+            return game.evaluate(player);
+        }
+
+        self.num_nodes_expanded += 1;
+
+        let maximizing = game.current() == player;
+        let mut best = if maximizing { i32::MIN } else { i32::MAX };
+
+        for placement in game.actions() {
+            let value = self.paranoid(&game.apply(&placement), player, alpha, beta, depth + 1);
+            if maximizing {
+                best = best.max(value);
+                alpha = alpha.max(best);
+            } else {
+                best = best.min(value);
+                beta = beta.min(best);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Position;
+    use crate::rolit::{Colour, RolitBoard, RolitBot, RolitGame};
+    use crate::rolit::Colour::{Blue, Green, Red, Yellow};
+
+    #[test]
+    fn parse() {
+        let board = RolitBoard::new();
+        assert_eq!(RolitBoard::parse(&board.to_string()).unwrap(), board);
+        assert_eq!(board.ball(&Position::new(3, 3)), Some(Red));
+        assert_eq!(board.ball(&Position::new(4, 4)), Some(Blue));
+        assert_eq!(board.counts(), [1, 1, 1, 1]);
+
+        assert!(RolitBoard::parse("RGYB\nEEEE\nEEEE\nEEEL").is_err());
+        assert!(RolitBoard::with_size(5).is_err());
+        assert!(Colour::parse('X').is_err());
+    }
+
+    #[test]
+    fn actions() {
+        let game = RolitGame::new(RolitBoard::new(), 4, Red).unwrap();
+
+        // Red can capture yellow, green and blue on the first move, so it must
+        let actions = game.actions();
+        assert_eq!(actions, vec![Position::new(3, 5), Position::new(5, 3), Position::new(5, 5)]);
+
+        let game = game.result(&Position::new(5, 5)).unwrap();
+        assert_eq!(game.board().ball(&Position::new(4, 4)), Some(Red));
+        assert_eq!(game.board().counts(), [3, 1, 1, 0]);
+        assert_eq!(game.current(), Green);
+        assert!(game.result(&Position::new(0, 0)).is_err());
+
+        // Without a capture, any square next to a ball can be played
+        let board = RolitBoard::parse("EEEE\nERYE\nEEEE\nEEEE").unwrap();
+        let game = RolitGame::new(board, 2, Green).unwrap();
+        assert_eq!(game.actions().len(), 10);
+        assert_eq!(game.result(&Position::new(1, 0)).unwrap().current(), Red);
+
+        assert!(RolitGame::new(RolitBoard::new(), 5, Red).is_err());
+        assert!(RolitGame::new(RolitBoard::new(), 3, Blue).is_err());
+    }
+
+    #[test]
+    fn winners() {
+        let board = RolitBoard::parse("RRRR\nGGGG\nYYYY\nBBBB").unwrap();
+        assert_eq!(RolitGame::new(board.clone(), 4, Red).unwrap().winners(), vec![Red, Green, Yellow, Blue]);
+
+        let board = RolitBoard::parse("RRRR\nRGGG\nYYYY\nBBBB").unwrap();
+        let game = RolitGame::new(board, 3, Red).unwrap();
+        assert!(game.is_over());
+        assert_eq!(game.winners(), vec![Red]);
+    }
+
+    #[test]
+    fn decide() {
+        let game = RolitGame::new(RolitBoard::new(), 2, Red).unwrap();
+        let placement = RolitBot::new(3).decide(&game).unwrap();
+        assert!(game.actions().contains(&placement));
+
+        // Every player makes a legal move until the board is full
+        let mut game = RolitGame::new(RolitBoard::with_size(6).unwrap(), 4, Red).unwrap();
+        let mut bot = RolitBot::new(1);
+        while let Some(placement) = bot.decide(&game) {
+            game = game.result(&placement).unwrap();
+        }
+        assert!(game.is_over());
+        assert!(!game.winners().is_empty());
+    }
+}
@@ -0,0 +1,942 @@
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use rocket::futures::Stream;
+use rocket::http::Status;
+use rocket::response::stream::{Event, EventStream, stream};
+use rocket::serde::json::Json;
+use rocket::tokio::select;
+use rocket::tokio::sync::broadcast;
+use rocket::tokio::sync::broadcast::error::RecvError;
+use rocket::tokio::time::sleep;
+use rocket::{Route, Shutdown, State};
+use rocket_okapi::okapi::openapi3::OpenApi;
+use rocket_okapi::settings::OpenApiSettings;
+use rocket_okapi::{openapi, openapi_get_routes_spec};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::api::{ApiError, ApiResult, BoardSetup};
+use crate::board::{Board, Disk, Position};
+use crate::bot::Mistakes;
+use crate::calibration::calibration;
+use crate::clock::{Clock, ClockState, TimeControl};
+use crate::difficulty::{Adaptive, Difficulty, ratings};
+use crate::engine::Engine;
+use crate::errors::Error;
+use crate::errors::Error::InvalidArgument;
+use crate::game::{Action, Game, MoveOutcome, Player, Variant};
+use crate::settings::SessionSettings;
+
+/// Number of events kept for subscribers that fall behind
+const EVENT_CAPACITY: usize = 64;
+
+/// Server-sent events of a game
+type Events = EventStream<Pin<Box<dyn Stream<Item = Event> + Send>>>;
+
+/// Who plays one side of a game
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Participant {
+    Bot,
+    Human { name: String },
+}
+
+/// Participant sitting at a side, with the secret that authorizes their moves
+struct Seat {
+    participant: Participant,
+    token: Option<String>,
+}
+
+/// Opponent of the player creating a game
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    /// The bot plays the other side
+    #[default]
+    Bot,
+    /// Another human joins through the server to play the other side
+    Online,
+}
+
+/// Event pushed to the subscribers of a game
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GameEvent {
+    /// Current state of the game, sent when subscribing
+    State { game: SessionGame },
+    /// A participant took a side of the game
+    Joined { side: Disk, participant: Participant },
+    /// A side placed a disk
+    Move { side: Disk, position: Position, outcome: MoveOutcome, game: SessionGame, clock: Option<ClockState> },
+    /// The bot finished searching another depth
+    Thinking { depth: u32, best: Position },
+    /// A side had no actions and passed the turn
+    Pass { side: Disk, game: SessionGame },
+    /// A side ran out of time and lost the game
+    Timeout { side: Disk },
+    /// The game is over, which a draw has no winner of
+    GameOver { winner: Option<Disk> },
+}
+
+impl GameEvent {
+
+    /// Returns the name of this event in the event stream
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameEvent::State { .. } => "state",
+            GameEvent::Joined { .. } => "joined",
+            GameEvent::Move { .. } => "move",
+            GameEvent::Thinking { .. } => "thinking",
+            GameEvent::Pass { .. } => "pass",
+            GameEvent::Timeout { .. } => "timeout",
+            GameEvent::GameOver { .. } => "game_over",
+        }
+    }
+}
+
+/// Clears the thinking flag of a session when dropped, even if the request playing for the bot is cancelled
+struct ThinkingGuard<'a>(&'a AtomicBool);
+
+impl Drop for ThinkingGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Game played through the server, which pushes its events to the subscribers
+pub struct Session {
+    game: Mutex<Game>,
+    seats: Mutex<HashMap<Disk, Seat>>,
+    clock: Mutex<Option<Clock>>,
+    intelligence: Option<u32>,
+    /// Intelligence of the bot following how well the human does, instead of the fixed one
+    adaptive: Option<Mutex<Adaptive>>,
+    mistakes: Option<Mistakes>,
+    events: broadcast::Sender<GameEvent>,
+    thinking: AtomicBool,
+    watching: AtomicBool,
+    last_active: Mutex<Instant>,
+}
+
+impl Session {
+
+    /// Creates a new session of the given game, whose clock starts once both sides are taken
+    fn new(game: Game, clock: Option<Clock>, intelligence: Option<u32>, adaptive: Option<Adaptive>,
+           mistakes: Option<Mistakes>) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+
+        // A set up board may leave the first player without actions
+        let mut game = game;
+        pass_while_stuck(&mut game);
+
+        Self {
+            game: Mutex::new(game),
+            seats: Mutex::new(HashMap::new()),
+            clock: Mutex::new(clock),
+            intelligence,
+            adaptive: adaptive.map(Mutex::new),
+            mistakes,
+            events,
+            thinking: AtomicBool::new(false),
+            watching: AtomicBool::new(false),
+            last_active: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Returns the current state of the game
+    pub fn game(&self) -> Game {
+        self.game.lock().unwrap().clone()
+    }
+
+    /// Returns the current state of the game for the clients, where losing on time ends the game
+    pub fn game_response(&self) -> SessionGame {
+        let mut response = SessionGame::from(&self.game());
+
+        if let Some(player) = self.flagged() {
+            response.is_over = true;
+            response.winner = Some(player.opponent().disk());
+        }
+        response
+    }
+
+    /// Returns the intelligence the bot plays with
+    pub fn intelligence(&self) -> Option<u32> {
+        match &self.adaptive {
+            Some(adaptive) => Some(adaptive.lock().unwrap().intelligence()),
+            None => self.intelligence,
+        }
+    }
+
+    /// Returns the strength of the bot, if it plays in this game
+    pub fn difficulty(&self) -> Option<Difficulty> {
+        let plays = self.players().iter().any(|player| player.participant == Participant::Bot);
+        let intelligence = self.intelligence().filter(|_| plays)?;
+
+        Some(Difficulty { intelligence, elo: ratings().elo(intelligence) })
+    }
+
+    /// Returns the current state of the clock
+    pub fn clock(&self) -> Option<ClockState> {
+        self.clock.lock().unwrap().as_ref().map(|clock| clock.state(Instant::now()))
+    }
+
+    /// Returns the player who ran out of time
+    pub fn flagged(&self) -> Option<Player> {
+        self.clock.lock().unwrap().as_ref().and_then(|clock| clock.flagged())
+    }
+
+    /// Ends the game if the player to move ran out of time, and returns how long until that happens otherwise
+    pub fn check_clock(&self) -> Option<Duration> {
+        let mut clock = self.clock.lock().unwrap();
+        let clock = clock.as_mut()?;
+        let now = Instant::now();
+
+        if let Some(player) = clock.check(now) {
+            self.publish_timeout(player);
+            return None;
+        }
+        clock.until_flag(now)
+    }
+
+    /// Ends the game as soon as the player to move runs out of time, even if no request comes in meanwhile
+    fn watch_clock(self: &Arc<Self>) {
+        if self.clock.lock().unwrap().is_none() || self.watching.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let session = self.clone();
+        rocket::tokio::spawn(async move {
+            while let Some(delay) = session.check_clock() {
+                sleep(delay + Duration::from_millis(1)).await;
+            }
+            session.watching.store(false, Ordering::SeqCst);
+        });
+    }
+
+    /// Sends the events of the given player losing on time
+    fn publish_timeout(&self, player: Player) {
+        self.publish(GameEvent::Timeout { side: player.disk() });
+        self.publish(GameEvent::GameOver { winner: Some(player.opponent().disk()) });
+    }
+
+    /// Returns the participants of the game with their sides, dark first
+    pub fn players(&self) -> Vec<PlayerResponse> {
+        let seats = self.seats.lock().unwrap();
+
+        [Disk::Dark, Disk::Light].into_iter()
+            .filter_map(|side| seats.get(&side).map(|seat| PlayerResponse {
+                side,
+                participant: seat.participant.clone(),
+            }))
+            .collect()
+    }
+
+    /// Seats the given participant at the first free side and returns the side with its secret token
+    fn sit(&self, participant: Participant) -> Result<(Disk, String), Error> {
+        let mut seats = self.seats.lock().unwrap();
+
+        let side = [Disk::Dark, Disk::Light].into_iter()
+            .find(|side| !seats.contains_key(side))
+            .ok_or_else(|| InvalidArgument("The game already has two players".to_string()))?;
+
+        let token = random_id();
+        seats.insert(side, Seat {
+            token: (participant != Participant::Bot).then(|| token.clone()),
+            participant: participant.clone(),
+        });
+        let is_full = seats.len() == 2;
+        drop(seats);
+
+        self.publish(GameEvent::Joined { side, participant });
+
+        if is_full {
+            let game = self.game.lock().unwrap();
+            if let Some(clock) = self.clock.lock().unwrap().as_mut() {
+                if !game.is_over() {
+                    clock.start(game.current_player(), Instant::now());
+                }
+            }
+        }
+        Ok((side, token))
+    }
+
+    /// Seats a human with the given name at the free side of the game
+    pub fn join(&self, name: String) -> Result<(Disk, String), Error> {
+        self.sit(Participant::Human { name })
+    }
+
+    /// Returns the side of the human holding the given token
+    pub fn side_of(&self, token: &str) -> Option<Disk> {
+        self.seats.lock().unwrap().iter()
+            .find(|(_, seat)| seat.token.as_deref() == Some(token))
+            .map(|(side, _)| *side)
+    }
+
+    /// Checks if the bot plays the side of the given player
+    fn is_bot(&self, player: Player) -> bool {
+        self.seats.lock().unwrap().get(&player.disk())
+            .is_some_and(|seat| seat.participant == Participant::Bot)
+    }
+
+    /// Subscribes to the events of this session
+    pub fn subscribe(&self) -> broadcast::Receiver<GameEvent> {
+        self.events.subscribe()
+    }
+
+    /// Sends the given event to the subscribers
+    fn publish(&self, event: GameEvent) {
+        // Having no subscribers isn't an error
+        let _ = self.events.send(event);
+    }
+
+    /// Checks if this session had no moves for the given duration
+    fn is_idle(&self, timeout: Duration) -> bool {
+        self.last_active.lock().unwrap().elapsed() >= timeout
+    }
+
+    /// Applies the given action to the game and passes the turn while the next player has no actions
+    pub fn play(&self, action: Action) -> Result<Game, Error> {
+        if self.seats.lock().unwrap().len() < 2 {
+            return Err(InvalidArgument("Waiting for an opponent to join".to_string()));
+        }
+        let by_human = !self.is_bot(action.player());
+
+        let mut game = self.game.lock().unwrap();
+        let mut clock = self.clock.lock().unwrap();
+
+        if game.is_over() || clock.as_ref().is_some_and(|clock| clock.flagged().is_some()) {
+            return Err(InvalidArgument("The game is already over".to_string()));
+        }
+        if action.player() != game.current_player() {
+            return Err(InvalidArgument(format!("It is not the turn of {}", action.player().disk())));
+        }
+        if !game.actions(action.player()).any(|act| act == action) {
+            return Err(InvalidArgument("Invalid action for the given player".to_string()));
+        }
+
+        let now = Instant::now();
+        if let Some(clock) = clock.as_mut() {
+            if !clock.stop(now) {
+                self.publish_timeout(action.player());
+                return Err(InvalidArgument(format!("{} ran out of time", action.player().disk())));
+            }
+        }
+
+        let (result, outcome) = game.result_with_outcome(&action);
+        *game = result.clone();
+        let passes = pass_while_stuck(&mut game);
+        if let Some(clock) = clock.as_mut() {
+            if !game.is_over() {
+                clock.start(game.current_player(), now);
+            }
+        }
+
+        let clock_state = clock.as_ref().map(|clock| clock.state(now));
+        self.publish(GameEvent::Move {
+            side: action.player().disk(),
+            position: action.placement().clone(),
+            outcome,
+            game: SessionGame::from(&result),
+            clock: clock_state,
+        });
+        for (player, game) in passes {
+            self.publish(GameEvent::Pass { side: player.disk(), game: SessionGame::from(&game) });
+        }
+
+        if game.is_over() {
+            self.publish(GameEvent::GameOver { winner: game.winner().map(|player| player.disk()) });
+        } else if let Some(adaptive) = self.adaptive.as_ref().filter(|_| by_human) {
+            adaptive.lock().unwrap().adapt(&calibration().predict(&game));
+        }
+
+        *self.last_active.lock().unwrap() = Instant::now();
+        Ok(game.clone())
+    }
+
+    /// Plays the turns of the bot until it is the turn of a human or the game is over
+    pub async fn play_bot(&self, engine: &Engine) -> Result<(), Error> {
+        if self.thinking.swap(true, Ordering::SeqCst) {
+            // Another request is already playing for the bot
+            return Ok(());
+        }
+        let _guard = ThinkingGuard(&self.thinking);
+
+        loop {
+            let game = self.game();
+            if game.is_over() || self.flagged().is_some() || !self.is_bot(game.current_player()) {
+                break;
+            }
+
+            let empties = game.board().empty_positions().count();
+            let time_limit = self.clock.lock().unwrap().as_ref()
+                .map(|clock| clock.budget(game.current_player(), Instant::now(), empties));
+
+            // Each move draws its own mistakes, which are the same whenever the game reaches it again
+            let mistakes = self.mistakes.map(|mistakes| Mistakes {
+                seed: mistakes.seed.wrapping_add(empties as u64),
+                ..mistakes
+            });
+
+            let events = self.events.clone();
+            let decision = engine.decide_with_progress(game, self.intelligence(), mistakes, time_limit,
+                                                       move |depth, action| {
+                let _ = events.send(GameEvent::Thinking { depth, best: action.placement().clone() });
+            }).await;
+
+            match decision.and_then(|(action, _)| self.play(action)) {
+                // Losing on time ends the game rather than failing the request
+                Err(_) if self.flagged().is_some() => break,
+                result => result?,
+            };
+        }
+
+        Ok(())
+    }
+}
+
+/// Passes the turn while the player to move has no actions and the game isn't over, returning each player who
+/// passed with the game after the pass
+fn pass_while_stuck(game: &mut Game) -> Vec<(Player, Game)> {
+    let mut passes = Vec::new();
+
+    while !game.is_over() && game.actions(game.current_player()).next().is_none() {
+        let player = game.current_player();
+        *game = game.pass();
+        passes.push((player, game.clone()));
+    }
+    passes
+}
+
+/// Returns a new random identifier that is hard to guess
+fn random_id() -> String {
+    format!("{:016x}{:016x}", rand::random::<u64>(), rand::random::<u64>())
+}
+
+/// Sessions of the games being played through the server
+pub struct Sessions {
+    settings: SessionSettings,
+    sessions: Mutex<HashMap<String, Arc<Session>>>,
+}
+
+impl Sessions {
+
+    /// Creates a new empty set of sessions
+    pub fn new(settings: SessionSettings) -> Self {
+        Self {
+            settings,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Starts a new session of the given game, dropping the idle ones first
+    pub fn create(&self, game: Game, clock: Option<Clock>, intelligence: Option<u32>, adaptive: Option<Adaptive>,
+                  mistakes: Option<Mistakes>) -> Result<(String, Arc<Session>), Error> {
+        let mut sessions = self.sessions.lock().unwrap();
+        self.evict_idle(&mut sessions);
+
+        if sessions.len() >= self.settings.max_sessions {
+            return Err(InvalidArgument("Too many games are being played".to_string()));
+        }
+
+        let id = random_id();
+        let session = Arc::new(Session::new(game, clock, intelligence, adaptive, mistakes));
+        sessions.insert(id.clone(), session.clone());
+
+        Ok((id, session))
+    }
+
+    /// Returns the session of the given id, dropping the idle ones first
+    pub fn get(&self, id: &str) -> Option<Arc<Session>> {
+        let mut sessions = self.sessions.lock().unwrap();
+        self.evict_idle(&mut sessions);
+
+        sessions.get(id).cloned()
+    }
+
+    /// Ends the session of the given id
+    pub fn remove(&self, id: &str) {
+        self.sessions.lock().unwrap().remove(id);
+    }
+
+    /// Drops the sessions idle for longer than the timeout
+    fn evict_idle(&self, sessions: &mut HashMap<String, Arc<Session>>) {
+        let timeout = Duration::from_secs(self.settings.idle_timeout);
+        sessions.retain(|_, session| !session.is_idle(timeout));
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct CreateSessionRequest {
+    /// Board to start from
+    #[serde(flatten)]
+    pub setup: BoardSetup,
+    /// Side to move first, which is the dark side by default
+    pub first: Option<Disk>,
+    #[serde(default)]
+    pub mode: Mode,
+    /// Name of the player creating the game, who takes the dark side
+    pub name: Option<String>,
+    /// Time control of the game, which is untimed by default
+    pub clock: Option<TimeControl>,
+    /// Intelligence of the bot when playing against it
+    pub intelligence: Option<u32>,
+    /// Estimated Elo rating of the bot when playing against it without an intelligence
+    pub elo: Option<f64>,
+    /// Whether the bot gets weaker while it leads and stronger while it trails, starting from its intelligence
+    #[serde(default)]
+    pub adaptive: bool,
+    /// Mistakes the bot makes for a more human play, instead of always playing the best move found
+    pub mistakes: Option<Mistakes>,
+    #[serde(default)]
+    pub variant: Variant,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct JoinRequest {
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct MoveRequest {
+    pub position: Position,
+    /// Token given to the player when creating or joining the game
+    pub token: String,
+}
+
+/// State of a game played through the server, which tells the players apart by their sides
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SessionGame {
+    pub board: Board,
+    /// Side to move
+    pub current_side: Disk,
+    pub variant: Variant,
+    pub is_over: bool,
+    /// Side with the most disks once the game is over, which a draw has none of
+    pub winner: Option<Disk>,
+}
+
+impl From<&Game> for SessionGame {
+    fn from(game: &Game) -> Self {
+        Self {
+            board: game.board().clone(),
+            current_side: game.current_player().disk(),
+            variant: game.variant(),
+            is_over: game.is_over(),
+            winner: if game.is_over() { game.winner().map(|player| player.disk()) } else { None },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PlayerResponse {
+    pub side: Disk,
+    pub participant: Participant,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SessionResponse {
+    pub id: String,
+    pub game: SessionGame,
+    pub players: Vec<PlayerResponse>,
+    pub clock: Option<ClockState>,
+    /// Current strength of the bot, when it plays
+    pub bot: Option<Difficulty>,
+}
+
+impl SessionResponse {
+
+    /// Creates the response of the given session
+    fn new(id: &str, session: &Session) -> Self {
+        Self {
+            id: id.to_string(),
+            game: session.game_response(),
+            players: session.players(),
+            clock: session.clock(),
+            bot: session.difficulty(),
+        }
+    }
+}
+
+/// Game as seen by one of its players, who moves with the token
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SeatResponse {
+    pub side: Disk,
+    pub token: String,
+    #[serde(flatten)]
+    pub session: SessionResponse,
+}
+
+/// Returns the session of the given id or a not found error
+fn find(sessions: &Sessions, id: &str) -> Result<Arc<Session>, ApiError> {
+    sessions.get(id).ok_or_else(|| ApiError::new(Status::NotFound, "No game with the given id"))
+}
+
+/// Name of the humans who don't give one
+const ANONYMOUS: &str = "Anonymous";
+
+/// Starts a new game against the bot or another human, taking its dark side
+///
+/// The bot moves right away when it plays first, while an online game waits for the opponent to join.
+#[openapi(tag = "games")]
+#[post("/", data = "<request>")]
+async fn create(request: Json<CreateSessionRequest>, sessions: &State<Sessions>, engine: &State<Engine>)
+    -> ApiResult<SeatResponse> {
+    let CreateSessionRequest { setup, first, mode, name, clock, intelligence, elo, adaptive, mistakes, variant }
+        = request.into_inner();
+    if let Some(mistakes) = &mistakes {
+        mistakes.validate().map_err(|e| ApiError::bad_request(&e.to_string()))?;
+    }
+    let board = setup.build().map_err(|e| ApiError::bad_request(&e.to_string()))?;
+    let game = Game::parse(board, Player::of(first.unwrap_or(Disk::Dark))).with_variant(variant);
+    let clock = clock.map(Clock::new).transpose()
+        .map_err(|e| ApiError::bad_request(&e.to_string()))?;
+
+    let intelligence = engine.intelligence_for(intelligence, elo);
+    let adaptive = (adaptive && mode == Mode::Bot).then(|| Adaptive::new(intelligence, engine.max_intelligence()));
+
+    let (id, session) = sessions.create(game, clock, Some(intelligence), adaptive, mistakes)
+        .map_err(|e| ApiError::new(Status::ServiceUnavailable, &e.to_string()))?;
+    let (side, token) = session.join(name.unwrap_or_else(|| ANONYMOUS.to_string()))
+        .map_err(|e| ApiError::bad_request(&e.to_string()))?;
+
+    if mode == Mode::Bot {
+        session.sit(Participant::Bot)
+            .map_err(|e| ApiError::bad_request(&e.to_string()))?;
+        session.watch_clock();
+        if let Err(e) = session.play_bot(engine).await {
+            // The game can't be played, so it isn't kept until it turns idle
+            sessions.remove(&id);
+            return Err(ApiError::bad_request(&e.to_string()));
+        }
+    }
+
+    Ok(Json(SeatResponse { side, token, session: SessionResponse::new(&id, &session) }))
+}
+
+/// Takes the free side of an online game
+#[openapi(tag = "games")]
+#[post("/<id>/join", data = "<request>")]
+fn join(id: &str, request: Json<JoinRequest>, sessions: &State<Sessions>) -> ApiResult<SeatResponse> {
+    let session = find(sessions, id)?;
+
+    let name = request.into_inner().name.unwrap_or_else(|| ANONYMOUS.to_string());
+    let (side, token) = session.join(name)
+        .map_err(|e| ApiError::new(Status::Conflict, &e.to_string()))?;
+    session.watch_clock();
+
+    Ok(Json(SeatResponse { side, token, session: SessionResponse::new(id, &session) }))
+}
+
+/// Returns the current state of the game
+#[openapi(tag = "games")]
+#[get("/<id>")]
+fn get(id: &str, sessions: &State<Sessions>) -> ApiResult<SessionResponse> {
+    let session = find(sessions, id)?;
+
+    Ok(Json(SessionResponse::new(id, &session)))
+}
+
+/// Streams the events of the game as server-sent events, starting with its current state
+///
+/// Anyone knowing the id can subscribe, so spectators receive the same events as the players.
+#[openapi(tag = "games")]
+#[get("/<id>/events")]
+fn events(id: &str, sessions: &State<Sessions>, mut shutdown: Shutdown) -> Result<Events, ApiError> {
+    let session = find(sessions, id)?;
+    let mut receiver = session.subscribe();
+    let state = GameEvent::State { game: session.game_response() };
+
+    let stream = stream! {
+        yield Event::json(&state).event(state.name());
+
+        loop {
+            let event = select! {
+                event = receiver.recv() => match event {
+                    Ok(event) => event,
+                    Err(RecvError::Closed) => break,
+                    Err(RecvError::Lagged(_)) => continue,
+                },
+                _ = &mut shutdown => break,
+            };
+
+            yield Event::json(&event).event(event.name());
+        }
+    };
+
+    Ok(EventStream::from(Box::pin(stream) as Pin<Box<dyn Stream<Item = Event> + Send>>))
+}
+
+/// Places a disk for the player holding the token and lets the bot reply, pushing every step to the event stream
+#[openapi(tag = "games")]
+#[post("/<id>/moves", data = "<request>")]
+async fn play(id: &str, request: Json<MoveRequest>, sessions: &State<Sessions>, engine: &State<Engine>)
+    -> ApiResult<SessionResponse> {
+    let session = find(sessions, id)?;
+    let MoveRequest { position, token } = request.into_inner();
+
+    let side = session.side_of(&token)
+        .ok_or_else(|| ApiError::new(Status::Forbidden, "The token doesn't belong to a player of the game"))?;
+
+    session.check_clock();
+    session.play(Action::parse(Player::of(side), position))
+        .map_err(|e| ApiError::bad_request(&e.to_string()))?;
+    session.play_bot(engine).await
+        .map_err(|e| ApiError::bad_request(&e.to_string()))?;
+
+    Ok(Json(SessionResponse::new(id, &session)))
+}
+
+/// Returns the routes of the games with their OpenAPI specification
+pub fn routes(settings: &OpenApiSettings) -> (Vec<Route>, OpenApi) {
+    let settings = settings.clone();
+    openapi_get_routes_spec![settings: create, join, get, events, play]
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::http::{ContentType, Status};
+    use rocket::local::blocking::Client;
+    use rocket_okapi::settings::OpenApiSettings;
+    use serde_json::json;
+
+    use crate::api::catchers;
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::board::{Board, Position};
+    use crate::board::Disk::{Dark, Light};
+    use crate::difficulty::{Adaptive, Difficulty, ratings};
+    use crate::engine::Engine;
+    use crate::game::{Action, Game};
+    use crate::game::Player::Human;
+    use crate::session::{GameEvent, Participant, routes, SeatResponse, SessionResponse, Sessions};
+    use crate::settings::{EngineSettings, SessionSettings};
+
+    fn client() -> Client {
+        let rocket = rocket::build()
+            .mount("/games", routes(&OpenApiSettings::default()).0)
+            .register("/games", catchers())
+            .manage(Engine::new(EngineSettings::default()).unwrap())
+            .manage(Sessions::new(SessionSettings::default()));
+
+        Client::tracked(rocket).unwrap()
+    }
+
+    #[test]
+    fn play() {
+        let client = client();
+
+        let response = client.post("/games")
+            .header(ContentType::JSON)
+            .body(json!({ "intelligence": 1 }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let SeatResponse { side, token, session } = response.into_json::<SeatResponse>().unwrap();
+        assert_eq!(side, Dark);
+        assert_eq!(session.game.current_side, Dark);
+        assert_eq!(session.players[1].participant, Participant::Bot);
+
+        let mut events = client.rocket().state::<Sessions>().unwrap()
+            .get(&session.id).unwrap()
+            .subscribe();
+
+        let response = client.post(format!("/games/{}/moves", session.id))
+            .header(ContentType::JSON)
+            .body(json!({ "position": { "row": 2, "col": 4 }, "token": "wrong" }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+
+        let response = client.post(format!("/games/{}/moves", session.id))
+            .header(ContentType::JSON)
+            .body(json!({ "position": { "row": 2, "col": 4 }, "token": token }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.into_json::<SessionResponse>().unwrap().game.current_side, Dark);
+
+        match events.try_recv().unwrap() {
+            GameEvent::Move { side, position, outcome, game, .. } => {
+                assert_eq!(side, Dark);
+                assert_eq!(position, Position::new(2, 4));
+                assert_eq!(outcome.flipped_positions().collect::<Vec<_>>(), vec![&Position::new(3, 4)]);
+                assert_eq!(game.current_side, Light);
+            },
+            event => panic!("Unexpected event: {:?}", event),
+        }
+        assert!(matches!(events.try_recv().unwrap(), GameEvent::Thinking { depth: 1, .. }));
+        assert!(matches!(events.try_recv().unwrap(), GameEvent::Thinking { depth: 2, .. }));
+        assert!(matches!(events.try_recv().unwrap(), GameEvent::Move { .. }));
+
+        let response = client.post(format!("/games/{}/moves", session.id))
+            .header(ContentType::JSON)
+            .body(json!({ "position": { "row": 0, "col": 0 }, "token": token }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let response = client.get("/games/unknown").dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    #[test]
+    fn stuck_start() {
+        let client = client();
+
+        // Dark can't move on the given board, so the bot plays light right away
+        let board = Board::parse("LDEE\nEEEE\nEEEE\nEEEE".to_string()).unwrap();
+        let response = client.post("/games")
+            .header(ContentType::JSON)
+            .body(json!({ "board": board, "intelligence": 1 }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let session = response.into_json::<SeatResponse>().unwrap().session;
+        assert_eq!(session.game.board.disk(&Position::new(0, 2)), Some(Light));
+        assert!(session.game.is_over);
+
+        // Idle sessions are dropped when looked up
+        let sessions = Sessions::new(SessionSettings { idle_timeout: 0, ..SessionSettings::default() });
+        let (id, _) = sessions.create(Game::parse(Board::new(), Human), None, None, None, None).unwrap();
+        assert!(sessions.get(&id).is_none());
+    }
+
+    #[test]
+    fn online() {
+        let client = client();
+        let play = |id: &str, token: &str, row: usize, col: usize| {
+            client.post(format!("/games/{}/moves", id))
+                .header(ContentType::JSON)
+                .body(json!({ "position": { "row": row, "col": col }, "token": token }).to_string())
+                .dispatch()
+                .status()
+        };
+
+        let response = client.post("/games")
+            .header(ContentType::JSON)
+            .body(json!({ "mode": "online", "name": "alice" }).to_string())
+            .dispatch();
+        let alice = response.into_json::<SeatResponse>().unwrap();
+        let id = alice.session.id.clone();
+        assert_eq!(alice.side, Dark);
+        assert_eq!(alice.session.players.len(), 1);
+
+        // The game waits for the opponent
+        assert_eq!(play(&id, &alice.token, 2, 4), Status::BadRequest);
+
+        let mut events = client.rocket().state::<Sessions>().unwrap()
+            .get(&id).unwrap()
+            .subscribe();
+
+        let response = client.post(format!("/games/{}/join", id))
+            .header(ContentType::JSON)
+            .body(json!({ "name": "bob" }).to_string())
+            .dispatch();
+        let bob = response.into_json::<SeatResponse>().unwrap();
+        assert_eq!(bob.side, Light);
+        assert_eq!(bob.session.players[1].side, Light);
+        assert_eq!(bob.session.players[1].participant, Participant::Human { name: "bob".to_string() });
+        assert!(matches!(events.try_recv().unwrap(), GameEvent::Joined { side: Light, .. }));
+
+        let response = client.post(format!("/games/{}/join", id))
+            .header(ContentType::JSON)
+            .body(json!({}).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Conflict);
+
+        // Each player can only move on their own turn
+        assert_eq!(play(&id, &bob.token, 2, 3), Status::BadRequest);
+        assert_eq!(play(&id, &alice.token, 2, 4), Status::Ok);
+        assert_eq!(play(&id, &alice.token, 2, 5), Status::BadRequest);
+        assert_eq!(play(&id, &bob.token, 2, 3), Status::Ok);
+
+        assert!(matches!(events.try_recv().unwrap(), GameEvent::Move { .. }));
+        match events.try_recv().unwrap() {
+            GameEvent::Move { side, game, .. } => {
+                assert_eq!(side, Light);
+                assert_eq!(game.current_side, Dark);
+            },
+            event => panic!("Unexpected event: {:?}", event),
+        }
+
+        let session = client.get(format!("/games/{}", id)).dispatch().into_json::<SessionResponse>().unwrap();
+        assert_eq!(session.game.board.disk(&Position::new(2, 3)), Some(Light));
+        assert_eq!(session.bot, None);
+    }
+
+    #[test]
+    fn difficulty() {
+        let client = client();
+
+        let response = client.post("/games")
+            .header(ContentType::JSON)
+            .body(json!({ "elo": ratings().elo(2) }).to_string())
+            .dispatch();
+        let session = response.into_json::<SeatResponse>().unwrap().session;
+        assert_eq!(session.bot, Some(Difficulty { intelligence: 2, elo: ratings().elo(2) }));
+
+        // The bot leads by far after the move of the human, so it gets weaker
+        let board = Board::parse([
+            "LLLLLLLL", "LLLLLLLL", "LLLLLLLL", "LLLLLLLL",
+            "LLLLLLLL", "LLLLLLLL", "LLLLLLLL", "DLEEEELD",
+        ].join("\n")).unwrap();
+        let sessions = Sessions::new(SessionSettings::default());
+        let (_, session) = sessions.create(Game::parse(board, Human), None, Some(3), Some(Adaptive::new(3, 6)), None)
+            .unwrap();
+        session.join("alice".to_string()).unwrap();
+        session.sit(Participant::Bot).unwrap();
+
+        session.play(Action::parse(Human, Position::new(7, 2))).unwrap();
+        assert_eq!(session.intelligence(), Some(2));
+        assert_eq!(session.difficulty().map(|difficulty| difficulty.intelligence), Some(2));
+    }
+
+    #[test]
+    fn clock() {
+        let client = client();
+
+        let response = client.post("/games")
+            .header(ContentType::JSON)
+            .body(json!({ "intelligence": 1, "clock": { "mode": "fischer", "initial_ms": 60000, "increment_ms": 1000 } })
+                .to_string())
+            .dispatch();
+        let SeatResponse { token, session, .. } = response.into_json::<SeatResponse>().unwrap();
+        let clock = session.clock.unwrap();
+        assert_eq!(clock.running, Some(Dark));
+        assert!(clock.players[0].remaining_ms <= 60000);
+
+        let response = client.post(format!("/games/{}/moves", session.id))
+            .header(ContentType::JSON)
+            .body(json!({ "position": { "row": 2, "col": 4 }, "token": token }).to_string())
+            .dispatch();
+        let clock = response.into_json::<SessionResponse>().unwrap().clock.unwrap();
+        assert_eq!(clock.running, Some(Dark));
+        assert!(clock.players[0].remaining_ms > 60000);
+        assert!(clock.players[1].remaining_ms > 0);
+
+        // Runs out of time while waiting for the opponent to move
+        let response = client.post("/games")
+            .header(ContentType::JSON)
+            .body(json!({ "mode": "online", "clock": { "mode": "fischer", "initial_ms": 10, "increment_ms": 0 } })
+                .to_string())
+            .dispatch();
+        let alice = response.into_json::<SeatResponse>().unwrap();
+        let id = alice.session.id;
+        client.post(format!("/games/{}/join", id))
+            .header(ContentType::JSON)
+            .body(json!({}).to_string())
+            .dispatch();
+        thread::sleep(Duration::from_millis(50));
+
+        let response = client.post(format!("/games/{}/moves", id))
+            .header(ContentType::JSON)
+            .body(json!({ "position": { "row": 2, "col": 4 }, "token": alice.token }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let session = client.get(format!("/games/{}", id)).dispatch().into_json::<SessionResponse>().unwrap();
+        assert!(session.game.is_over);
+        assert_eq!(session.game.winner, Some(Light));
+        assert_eq!(session.clock.unwrap().flagged, Some(Dark));
+    }
+}
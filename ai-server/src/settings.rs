@@ -0,0 +1,214 @@
+use std::path::PathBuf;
+use std::thread;
+
+use rocket::figment::Figment;
+use serde::Deserialize;
+
+use crate::analysis::DEFAULT_SOLVE_TIME;
+use crate::bot::DEFAULT_CACHE_SIZE;
+use crate::cors::ANY_ORIGIN;
+use crate::errors::Error;
+use crate::errors::Error::InvalidConfig;
+use crate::game::PhaseThresholds;
+
+/// Settings of the server, read from Rocket.toml and `ROCKET_` environment variables
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub cors: CorsSettings,
+    pub engine: EngineSettings,
+    pub sessions: SessionSettings,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CorsSettings {
+    /// Exact origins, wildcard patterns such as `https://*.example.com` or regexes prefixed with `regex:`
+    pub allowed_origins: Vec<String>,
+    /// Methods that cross-origin requests can use
+    pub allowed_methods: Vec<String>,
+    /// Headers that cross-origin requests can send, where `*` allows any requested header
+    pub allowed_headers: Vec<String>,
+    /// Number of seconds browsers can cache the result of a preflight request
+    pub max_age: u64,
+    /// Whether cross-origin requests can include credentials
+    pub allow_credentials: bool,
+}
+
+impl Default for CorsSettings {
+    fn default() -> Self {
+        Self {
+            allowed_origins: vec![
+                "http://localhost".to_string(),
+                "http://localhost:443".to_string(),
+                "http://localhost:8080".to_string(),
+                "https://localhost".to_string(),
+                "https://localhost:80".to_string(),
+            ],
+            allowed_methods: vec!["GET".to_string(), "POST".to_string(), "OPTIONS".to_string()],
+            allowed_headers: vec!["*".to_string()],
+            max_age: 86400,
+            allow_credentials: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct EngineSettings {
+    /// Intelligence of the bot when a request doesn't specify one
+    pub default_intelligence: u32,
+    /// Highest intelligence a request can ask for
+    pub max_intelligence: u32,
+    /// Number of searches that can run at the same time
+    pub threads: usize,
+    /// Maximum number of evaluations each search keeps in its cache
+    pub cache_size: usize,
+    /// Number of milliseconds an analysis or a review can spend solving the end of the game exactly, after which
+    /// its moves are scored by the bot
+    pub solve_time: u64,
+    /// Opening book file
+    pub book_path: Option<PathBuf>,
+    /// JSON file of the evaluation weights
+    pub weights_path: Option<PathBuf>,
+    /// JSON file of the evaluation weights of anti games
+    pub anti_weights_path: Option<PathBuf>,
+    /// File of balanced openings for the XOT start, one line of moves per opening
+    pub openings_path: Option<PathBuf>,
+    /// JSON file of the win probability models, as written by the `calibrate` command
+    pub calibration_path: Option<PathBuf>,
+    /// Numbers of empty squares from which the middle and the end of the game start
+    pub phases: Option<PhaseThresholds>,
+    /// JSON file of the Elo ratings of each intelligence, as written by the `rate` command
+    pub ratings_path: Option<PathBuf>,
+}
+
+impl Default for EngineSettings {
+    fn default() -> Self {
+        Self {
+            default_intelligence: 3,
+            max_intelligence: 8,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            cache_size: DEFAULT_CACHE_SIZE,
+            solve_time: DEFAULT_SOLVE_TIME,
+            book_path: None,
+            weights_path: None,
+            anti_weights_path: None,
+            openings_path: None,
+            calibration_path: None,
+            phases: None,
+            ratings_path: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SessionSettings {
+    /// Maximum number of games that can be played at the same time
+    pub max_sessions: usize,
+    /// Number of seconds without a move after which a game is dropped
+    pub idle_timeout: u64,
+}
+
+impl Default for SessionSettings {
+    fn default() -> Self {
+        Self {
+            max_sessions: 1000,
+            idle_timeout: 3600,
+        }
+    }
+}
+
+impl Settings {
+
+    /// Extracts the settings from the given figment and validates them
+    pub fn load(figment: &Figment) -> Result<Self, Error> {
+        let settings: Self = figment.extract()
+            .map_err(|e| InvalidConfig(e.to_string()))?;
+
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// Checks if the values of these settings are usable
+    fn validate(&self) -> Result<(), Error> {
+        let engine = &self.engine;
+
+        if engine.default_intelligence > engine.max_intelligence {
+            return Err(InvalidConfig(format!(
+                "engine.default_intelligence ({}) must not exceed engine.max_intelligence ({})",
+                engine.default_intelligence, engine.max_intelligence)));
+        }
+
+        if engine.threads == 0 {
+            return Err(InvalidConfig("engine.threads must be at least 1".to_string()));
+        }
+
+        if let Some(phases) = &engine.phases {
+            phases.validate()?;
+        }
+
+        if engine.cache_size == 0 {
+            return Err(InvalidConfig("engine.cache_size must be at least 1".to_string()));
+        }
+
+        // Browsers refuse credentials from a response allowing any origin, and echoing every origin instead would let
+        // any site make requests with the credentials of its visitors
+        if self.cors.allow_credentials && self.cors.allowed_origins.iter().any(|origin| origin == ANY_ORIGIN) {
+            return Err(InvalidConfig(format!(
+                "cors.allowed_origins must not contain \"{}\" while cors.allow_credentials is true", ANY_ORIGIN)));
+        }
+
+        if self.sessions.max_sessions == 0 {
+            return Err(InvalidConfig("sessions.max_sessions must be at least 1".to_string()));
+        }
+
+        let paths = [&engine.book_path, &engine.weights_path, &engine.anti_weights_path, &engine.openings_path,
+            &engine.calibration_path, &engine.ratings_path];
+        for path in paths.into_iter().flatten() {
+            if !path.is_file() {
+                return Err(InvalidConfig(format!("File does not exist: {}", path.display())));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::figment::Figment;
+    use rocket::figment::providers::{Format, Toml};
+
+    use crate::settings::Settings;
+
+    #[test]
+    fn load() {
+        let settings = Settings::load(&Figment::new()).unwrap();
+        assert_eq!(settings.engine.default_intelligence, 3);
+        assert!(!settings.cors.allowed_origins.is_empty());
+
+        let figment = Figment::from(Toml::string("\
+        [cors]\n\
+        allowed_origins = [\"https://*.example.com\"]\n\
+        [engine]\n\
+        max_intelligence = 5\n\
+        threads = 2\n"));
+        let settings = Settings::load(&figment).unwrap();
+        assert_eq!(settings.cors.allowed_origins, vec!["https://*.example.com"]);
+        assert_eq!(settings.engine.max_intelligence, 5);
+        assert_eq!(settings.engine.threads, 2);
+
+        let figment = Figment::from(Toml::string("[engine]\ndefault_intelligence = 9\nmax_intelligence = 4"));
+        assert!(Settings::load(&figment).is_err());
+
+        let figment = Figment::from(Toml::string("[engine]\nbook_path = \"/no/such/book\""));
+        assert!(Settings::load(&figment).is_err());
+
+        let figment = Figment::from(Toml::string("[cors]\nallowed_origins = [\"*\"]"));
+        assert!(Settings::load(&figment).is_err());
+        let figment = Figment::from(Toml::string("[cors]\nallowed_origins = [\"*\"]\nallow_credentials = false"));
+        assert!(Settings::load(&figment).is_ok());
+    }
+}
@@ -0,0 +1,393 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+
+use crate::board::{Board, DEFAULT_BOARD_SIZE, Position};
+use crate::bot::Bot;
+use crate::calibration::Outcome;
+use crate::errors::Error;
+use crate::errors::Error::{InvalidArgument, ParseError};
+use crate::game::{Action, Game, Player, Weights};
+use crate::openings::Start;
+
+/// Number of standard deviations either side of the Elo difference in its 95% confidence interval
+pub const Z_95: f64 = 1.96;
+
+/// Settings of an engine playing in a tournament
+#[derive(Debug, Clone, PartialEq)]
+pub struct EngineConfig {
+    pub name: String,
+    pub intelligence: u32,
+    /// Weights to evaluate games with instead of the ones in use
+    pub weights: Option<Arc<Weights>>,
+}
+
+impl EngineConfig {
+
+    /// Parses the given comma separated settings, such as "name=new,depth=4,weights=weights.json".
+    /// The depth is required, and the name defaults to the settings themselves.
+    pub fn parse(s: &str) -> Result<Self, Error> {
+        let mut name = None;
+        let mut intelligence = None;
+        let mut weights = None;
+
+        for setting in s.split(',') {
+            let (key, value) = setting.split_once('=')
+                .ok_or_else(|| ParseError(format!("Invalid engine setting: {}", setting)))?;
+            match key.trim() {
+                "name" => name = Some(value.trim().to_string()),
+                "depth" => intelligence = Some(value.trim().parse()
+                    .map_err(|_| ParseError(format!("Invalid depth of the engine: {}", value)))?),
+                "weights" => weights = Some(Arc::new(Weights::load(Path::new(value.trim()))?)),
+                _ => return Err(ParseError(format!("Unknown engine setting: {}", key))),
+            }
+        }
+
+        Ok(Self {
+            name: name.unwrap_or_else(|| s.to_string()),
+            intelligence: intelligence.ok_or_else(|| ParseError(format!("Engine has no depth: {}", s)))?,
+            weights,
+        })
+    }
+
+    /// Creates the bot of this engine
+    fn bot(&self) -> Bot {
+        let mut bot = Bot::new(self.intelligence);
+        if let Some(weights) = &self.weights {
+            bot.set_weights(weights.clone());
+        }
+        bot
+    }
+}
+
+/// Game played in a tournament, written as the moves played from its opening
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TournamentGame {
+    /// Name of the engine playing dark, which moves first
+    pub dark: String,
+    /// Name of the engine playing light
+    pub light: String,
+    pub start: Board,
+    pub moves: Vec<Position>,
+    pub dark_disks: usize,
+    pub light_disks: usize,
+}
+
+impl TournamentGame {
+
+    /// Returns the outcome of this game for the engine of the given name
+    pub fn outcome(&self, name: &str) -> Outcome {
+        let (own, other) = if self.dark == name {
+            (self.dark_disks, self.light_disks)
+        } else {
+            (self.light_disks, self.dark_disks)
+        };
+
+        if own > other {
+            Outcome::Win
+        } else if own < other {
+            Outcome::Loss
+        } else {
+            Outcome::Draw
+        }
+    }
+}
+
+/// Plays a game between the given engines from the given board, where dark moves first
+pub fn play_game(dark: &EngineConfig, light: &EngineConfig, start: &Board) -> TournamentGame {
+    play_game_until(dark, light, start, &AtomicBool::new(false)).expect("Game is never stopped")
+}
+
+/// Plays a game like play_game, but abandons it before the next move once the given flag is set
+fn play_game_until(dark: &EngineConfig, light: &EngineConfig, start: &Board, stopped: &AtomicBool)
+    -> Option<TournamentGame> {
+    let mut game = Game::parse(start.clone(), Player::Human);
+    let mut dark_bot = dark.bot();
+    let mut light_bot = light.bot();
+    let mut moves = Vec::new();
+
+    while !game.is_over() {
+        if stopped.load(Ordering::Relaxed) {
+            return None;
+        }
+        let player = game.current_player();
+        if game.actions(player).next().is_none() {
+            game = game.pass();
+            continue;
+        }
+
+        // The bot always plays light, so the colours are swapped for the engine playing dark
+        let (action, _) = match player {
+            Player::Human => dark_bot.decide(&game.swap_colours()),
+            Player::Bot => light_bot.decide(&game),
+        }.expect("Player to move has actions");
+
+        game = game.result(&Action::parse(player, action.placement().clone()));
+        moves.push(action.placement().clone());
+    }
+
+    Some(TournamentGame {
+        dark: dark.name.clone(),
+        light: light.name.clone(),
+        start: start.clone(),
+        moves,
+        dark_disks: game.board().positions(Player::Human.disk()).count(),
+        light_disks: game.board().positions(Player::Bot.disk()).count(),
+    })
+}
+
+/// Plays pairs of games between the given engines on the given number of threads, where both games of a pair start
+/// from the same balanced opening chosen by the seed with the colours swapped.
+/// Each game is passed to the callback as soon as it ends, which returns whether to keep playing. Once it doesn't,
+/// no more games start and the games still being played are abandoned before their next move.
+/// The games passed to the callback are returned in the order they were scheduled.
+pub fn run_tournament<F>(first: &EngineConfig, second: &EngineConfig, pairs: usize, threads: usize, seed: u64,
+                         mut on_game: F) -> Result<Vec<TournamentGame>, Error>
+    where F: FnMut(&TournamentGame) -> bool {
+    if first.name == second.name {
+        return Err(InvalidArgument(format!("Engines must have different names: {}", first.name)));
+    }
+    if pairs == 0 {
+        return Err(InvalidArgument("At least one pair of games must be played".to_string()));
+    }
+
+    let openings = (0..pairs as u64)
+        .map(|i| Start::Xot.board(DEFAULT_BOARD_SIZE, seed.wrapping_add(i)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let next = AtomicUsize::new(0);
+    let stopped = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
+    let mut games = Vec::with_capacity(2 * pairs);
+
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            let sender = sender.clone();
+            let (next, stopped, openings) = (&next, &stopped, &openings);
+            scope.spawn(move || loop {
+                if stopped.load(Ordering::Relaxed) {
+                    break;
+                }
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(start) = openings.get(index / 2) else {
+                    break;
+                };
+                let game = if index % 2 == 0 {
+                    play_game_until(first, second, start, stopped)
+                } else {
+                    play_game_until(second, first, start, stopped)
+                };
+                let Some(game) = game else {
+                    break;
+                };
+                if sender.send((index, game)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        for (index, game) in receiver {
+            let keep_playing = on_game(&game);
+            games.push((index, game));
+            if !keep_playing {
+                stopped.store(true, Ordering::Relaxed);
+                break;
+            }
+        }
+    });
+
+    games.sort_by_key(|(index, _)| *index);
+    Ok(games.into_iter().map(|(_, game)| game).collect())
+}
+
+/// Returns the expected score of a player rated the given number of Elo points above its opponent
+pub fn elo_to_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// Returns the Elo difference at which a player is expected to score the given share of points
+pub fn score_to_elo(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// Decision of a sequential probability ratio test
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// The Elo difference is at least the upper bound tested
+    AcceptH1,
+    /// The Elo difference is at most the lower bound tested
+    AcceptH0,
+    /// More games are needed to decide
+    Continue,
+}
+
+/// Result of a sequential probability ratio test between two Elo differences
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    /// Log-likelihood ratio of the upper Elo difference against the lower one
+    pub llr: f64,
+    pub lower_bound: f64,
+    pub upper_bound: f64,
+}
+
+impl Sprt {
+
+    pub fn verdict(&self) -> Verdict {
+        if self.llr >= self.upper_bound {
+            Verdict::AcceptH1
+        } else if self.llr <= self.lower_bound {
+            Verdict::AcceptH0
+        } else {
+            Verdict::Continue
+        }
+    }
+}
+
+/// Wins, draws and losses of an engine
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Record {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl Record {
+
+    /// Returns the record of the engine of the given name over the given games
+    pub fn of<'a>(name: &str, games: impl IntoIterator<Item=&'a TournamentGame>) -> Self {
+        let mut record = Self::default();
+        for game in games {
+            record.add(game.outcome(name));
+        }
+        record
+    }
+
+    pub fn add(&mut self, outcome: Outcome) {
+        match outcome {
+            Outcome::Win => self.wins += 1,
+            Outcome::Draw => self.draws += 1,
+            Outcome::Loss => self.losses += 1,
+        }
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Returns the share of points scored, counting a draw as half a win
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
+
+    /// Returns the variance of the points scored in a game
+    fn variance(&self) -> f64 {
+        let score = self.score();
+        let games = self.games() as f64;
+        (self.wins as f64 * (1.0 - score).powi(2) + self.draws as f64 * (0.5 - score).powi(2) +
+            self.losses as f64 * score.powi(2)) / games
+    }
+
+    /// Returns the estimated Elo difference over the opponent
+    pub fn elo(&self) -> f64 {
+        score_to_elo(self.score())
+    }
+
+    /// Returns the confidence interval of the Elo difference, spanning the given number of standard deviations
+    pub fn elo_interval(&self, z: f64) -> (f64, f64) {
+        let error = z * (self.variance() / self.games() as f64).sqrt();
+        let score = self.score();
+        (score_to_elo((score - error).max(0.0)), score_to_elo((score + error).min(1.0)))
+    }
+
+    /// Tests whether the Elo difference is at least elo1 rather than at most elo0, with the given chances of
+    /// accepting elo1 wrongly and of accepting elo0 wrongly
+    pub fn sprt(&self, elo0: f64, elo1: f64, alpha: f64, beta: f64) -> Sprt {
+        let lower_bound = (beta / (1.0 - alpha)).ln();
+        let upper_bound = ((1.0 - beta) / alpha).ln();
+
+        let variance = self.variance();
+        let llr = if self.games() == 0 || variance == 0.0 {
+            0.0
+        } else {
+            // Normal approximation of the generalized SPRT on the score of each game
+            let (score0, score1) = (elo_to_score(elo0), elo_to_score(elo1));
+            self.games() as f64 * (score1 - score0) * (2.0 * self.score() - score0 - score1) / (2.0 * variance)
+        };
+
+        Sprt { llr, lower_bound, upper_bound }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+
+    use crate::board::Board;
+    use crate::calibration::Outcome;
+    use crate::tournament::{elo_to_score, EngineConfig, play_game, play_game_until, Record, run_tournament,
+                            score_to_elo, Verdict, Z_95};
+
+    #[test]
+    fn engines() {
+        let config = EngineConfig::parse("name=new,depth=2").unwrap();
+        assert_eq!(config.name, "new");
+        assert_eq!(config.intelligence, 2);
+        assert_eq!(EngineConfig::parse("depth=1").unwrap().name, "depth=1");
+
+        assert!(EngineConfig::parse("name=new").is_err());
+        assert!(EngineConfig::parse("depth=x").is_err());
+        assert!(EngineConfig::parse("speed=1,depth=1").is_err());
+        assert!(EngineConfig::parse("depth=1,weights=missing.json").is_err());
+    }
+
+    #[test]
+    fn games() {
+        let weak = EngineConfig::parse("name=weak,depth=0").unwrap();
+        let strong = EngineConfig::parse("name=strong,depth=1").unwrap();
+
+        let game = play_game(&weak, &strong, &Board::with_size(6).unwrap());
+        assert_eq!(game.dark, "weak");
+        assert!(game.dark_disks + game.light_disks <= 36);
+        assert!(!game.moves.is_empty());
+
+        let games = run_tournament(&weak, &strong, 2, 2, 0, |_| true).unwrap();
+        assert_eq!(games.len(), 4);
+        assert_eq!((games[0].dark.as_str(), games[1].dark.as_str()), ("weak", "strong"));
+        assert_eq!(games[0].start, games[1].start);
+        assert_eq!(Record::of("weak", &games).games(), 4);
+
+        assert!(run_tournament(&weak, &weak, 1, 1, 0, |_| true).is_err());
+        assert!(run_tournament(&weak, &strong, 0, 1, 0, |_| true).is_err());
+
+        // No more games start once the callback stops the tournament
+        let games = run_tournament(&weak, &strong, 3, 1, 0, |_| false).unwrap();
+        assert_eq!(games.len(), 1);
+        assert!(play_game_until(&weak, &strong, &Board::new(), &AtomicBool::new(true)).is_none());
+    }
+
+    #[test]
+    fn elo() {
+        assert!((elo_to_score(0.0) - 0.5).abs() < 1e-9);
+        assert!((score_to_elo(elo_to_score(100.0)) - 100.0).abs() < 1e-6);
+
+        let mut record = Record::default();
+        for _ in 0..600 {
+            record.add(Outcome::Win);
+        }
+        for _ in 0..400 {
+            record.add(Outcome::Loss);
+        }
+        assert!((record.elo() - 70.4).abs() < 0.1);
+        let (low, high) = record.elo_interval(Z_95);
+        assert!(low < record.elo() && record.elo() < high);
+        assert!(low > 0.0);
+
+        assert_eq!(record.sprt(0.0, 10.0, 0.05, 0.05).verdict(), Verdict::AcceptH1);
+        assert_eq!(record.sprt(150.0, 160.0, 0.05, 0.05).verdict(), Verdict::AcceptH0);
+        assert_eq!(Record { wins: 1, draws: 0, losses: 1 }.sprt(0.0, 10.0, 0.05, 0.05).verdict(), Verdict::Continue);
+    }
+}